@@ -1,60 +1,340 @@
+use std::path::Path;
 
 use render::Context;
+use render::config::Config;
+use error::Result;
+use vulkano::format::Format;
+use vulkano::swapchain::PresentMode;
 use winit::EventsLoop;
 use event::EventHandler;
+use input::InputEvent;
+use input::replay::{ Recording, RecordedFrame, Recorder, Playback };
 use time::Clock;
-use std::time;
 
 pub struct Application {
     pub ctx: Context,
-    events_loop: EventsLoop,
+    events_loop: Option<EventsLoop>,
     pub clock: Clock,
-    pub last_frame: time::Instant,
+    recorder: Option<Recorder>,
+    playback: Option<Playback>,
 }
 
 impl Application {
-    pub fn new(width: u32, height: u32, title: &str) -> Self {
-        let (ctx, events_loop) = Context::new(width, height, title);
-        let clock = Clock::default();
-        let last_frame = time::Instant::now();
+    pub fn new(width: u32, height: u32, title: &str) -> Result<Self> {
+        ApplicationBuilder::new(title)
+            .dimensions(width, height)
+            .build()
+    }
+
+    /// Creates an application without a window, rendering offscreen.
+    /// Drive it with `run_frames` and read the result with `Context::read_image`.
+    pub fn new_headless(width: u32, height: u32, title: &str) -> Result<Self> {
+        ApplicationBuilder::new(title)
+            .dimensions(width, height)
+            .headless(true)
+            .build()
+    }
 
+    fn from_context(ctx: Context, events_loop: Option<EventsLoop>, clock: Clock) -> Self {
         Self {
             ctx,
             events_loop,
             clock,
-            last_frame,
+            recorder: None,
+            playback: None,
         }
 
     }
 
-    pub fn run(&mut self, state: &mut EventHandler) {
-        let ctx = &mut self.ctx;
+    /// Records every frame's input and timestep to `path`, written as the
+    /// frames are run so that a crash doesn't lose them.
+    pub fn record<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.recorder = Some(Recorder::create(path, self.clock.get_tick_rate())?);
+        Ok(())
+    }
+
+    /// Replays a recording made with `record` instead of reading live
+    /// input. The run stops once the recording is exhausted.
+    pub fn replay<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let playback = Playback::new(Recording::load(path)?);
+        self.clock.set_tick_rate(playback.tick_rate());
+        self.playback = Some(playback);
+        Ok(())
+    }
 
-        let mut closed = false;
-        while !closed {
-            self.clock.tick();
+    /// Runs until the window is closed, `Context::quit` is called, a replay
+    /// ends or a frame fails to render.
+    pub fn run(&mut self, state: &mut EventHandler) -> Result<()> {
+        let result = self.run_loop(state, None);
+        self.finish_recording(result)
+    }
 
-            let now = time::Instant::now();
-            let delta = now - self.last_frame;
-            let delta_s = delta.as_secs() as f32 + delta.subsec_nanos() as f32 / 1_000_000_000.0;
-            self.last_frame = now;
-            let fps = (1.0 / delta_s) as i32;
-            println!("FPS: {}", fps);
-            //println!("FPS: {}", self.clock.get_fps());
+    /// Runs exactly `frames` frames, each doing a single fixed update
+    /// regardless of wall-clock time, so the outcome is the same on any
+    /// machine. Stops early if the window is closed.
+    pub fn run_frames(&mut self, state: &mut EventHandler, frames: u32) -> Result<()> {
+        let result = self.run_loop(state, Some(frames));
+        self.finish_recording(result)
+    }
 
-            self.events_loop.poll_events(|event| {
-                match event {
-                    winit::Event::WindowEvent { event: winit::WindowEvent::CloseRequested, .. } => closed = true,
-                    _ => (),
-                }
+    fn run_loop(&mut self, state: &mut EventHandler, frames: Option<u32>) -> Result<()> {
+        self.clock.reset();
+        let mut frame = 0;
+        while frames.map_or(true, |frames| frame < frames) {
+            if !self.frame(state, frames.is_some())? {
+                break;
+            }
+            frame += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Runs one frame, returns `false` when the application should stop.
+    fn frame(&mut self, state: &mut EventHandler, single_step: bool) -> Result<bool> {
+        self.clock.tick();
+
+        self.clock.profiler_mut().begin("events");
+        let (closed, events) = poll_events(&mut self.events_loop, &mut self.ctx);
+
+        let input = match self.playback {
+            Some(ref mut playback) => {
+                self.clock.discard_update_time();
+                playback.next_frame()
+            },
+            None => {
+                let steps = if single_step {
+                    self.clock.discard_update_time();
+                    1
+                } else {
+                    let mut steps = 0;
+                    while self.clock.check_update_time() {
+                        steps += 1;
+                    }
+                    steps
+                };
+                let alpha = if single_step { 0.0 } else { self.clock.get_alpha() };
+
+                Some(RecordedFrame {
+                    frame: self.clock.get_frame_count(),
+                    steps,
+                    dt: self.clock.get_fixed_dt(),
+                    alpha,
+                    events,
+                })
+            },
+        };
+        let input = match input {
+            Some(input) => input,
+            None => return Ok(false),
+        };
+
+        for event in input.events.iter() {
+            self.ctx.input.process(event);
+            state.input_event(&mut self.ctx, event);
+        }
+        self.clock.profiler_mut().end();
+
+        self.clock.profiler_mut().begin("update");
+        for step in 0 .. input.steps {
+            state.update(&mut self.ctx, input.dt);
+            if step == 0 {
+                self.ctx.input.end_step();
+            }
+        }
+        self.clock.profiler_mut().end();
 
-            });
+        self.clock.profiler_mut().begin("draw");
+        state.draw(&mut self.ctx, input.alpha);
+        self.clock.profiler_mut().end();
 
-            state.update(ctx);
-            state.draw(ctx);
-            ctx.update();
+        self.clock.profiler_mut().begin("render");
+        self.ctx.update()?;
+        self.clock.profiler_mut().end();
 
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.push(&input)?;
         }
+
+        Ok(!closed && !self.ctx.should_quit())
+    }
+
+    /// Completes the recording file. An error of the run takes precedence
+    /// over one finishing the recording, which is then only logged.
+    fn finish_recording(&mut self, result: Result<()>) -> Result<()> {
+        let finished = match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        };
+
+        match (result, finished) {
+            (Err(err), Err(finish_err)) => {
+                error!("Failed to finish the recording: {}", finish_err);
+                Err(err)
+            },
+            (result, finished) => result.and(finished),
+        }
+    }
+
+}
+
+pub struct ApplicationBuilder {
+    config: Config,
+    tick_rate: Option<u32>,
+}
+
+impl ApplicationBuilder {
+    pub fn new(title: &str) -> Self {
+        Self {
+            config: Config {
+                title: title.into(),
+                .. Config::default()
+            },
+            tick_rate: None,
+        }
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.config.title = title.into();
+        self
+    }
+
+    pub fn dimensions(mut self, width: u32, height: u32) -> Self {
+        self.config.width = width;
+        self.config.height = height;
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.config.resizable = resizable;
+        self
     }
 
-}
\ No newline at end of file
+    /// Goes fullscreen on the primary monitor.
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.config.fullscreen = fullscreen;
+        self
+    }
+
+    /// Renders offscreen instead of opening a window, see `Application::new_headless`.
+    pub fn headless(mut self, headless: bool) -> Self {
+        self.config.headless = headless;
+        self
+    }
+
+    /// Shorthand for `present_mode`: `Fifo` when enabled, `Immediate` otherwise.
+    pub fn vsync(self, vsync: bool) -> Self {
+        self.present_mode(if vsync { PresentMode::Fifo } else { PresentMode::Immediate })
+    }
+
+    pub fn present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.config.present_mode = present_mode;
+        self
+    }
+
+    /// MSAA sample count, 1 to disable. Capped to what the device supports.
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.config.samples = samples;
+        self
+    }
+
+    /// How many frames the CPU may queue ahead of the GPU, at least 1.
+    pub fn frames_in_flight(mut self, frames: usize) -> Self {
+        self.config.frames_in_flight = frames;
+        self
+    }
+
+    pub fn depth_format(mut self, depth_format: Format) -> Self {
+        self.config.depth_format = depth_format;
+        self
+    }
+
+    pub fn clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.config.clear_color = clear_color;
+        self
+    }
+
+    /// Enables the validation layers, defaults to whether this is a debug build.
+    pub fn validation(mut self, validation: bool) -> Self {
+        self.config.validation = validation;
+        self
+    }
+
+    /// Forces a device by enumeration index (`"1"`) or part of its name
+    /// (`"llvmpipe"`). `SW3D_DEVICE` overrides it.
+    pub fn preferred_gpu(mut self, selector: &str) -> Self {
+        self.config.preferred_gpu = Some(selector.into());
+        self
+    }
+
+    /// Reloads textures and shaders when they change on disk. If
+    /// `shader_dir` can't be watched a warning is logged and the
+    /// application runs without it.
+    pub fn hot_reload(mut self, enabled: bool) -> Self {
+        self.config.hot_reload = enabled;
+        self
+    }
+
+    pub fn shader_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.config.shader_dir = path.as_ref().to_owned();
+        self
+    }
+
+    /// Number of fixed simulation steps per second, see `Clock::set_tick_rate`.
+    pub fn tick_rate(mut self, ticks_per_second: u32) -> Self {
+        self.tick_rate = Some(ticks_per_second);
+        self
+    }
+
+    pub fn build(self) -> Result<Application> {
+        let mut clock = Clock::default();
+        if let Some(tick_rate) = self.tick_rate {
+            clock.set_tick_rate(tick_rate);
+        }
+
+        if self.config.headless {
+            let ctx = Context::new_headless(self.config)?;
+            Ok(Application::from_context(ctx, None, clock))
+        } else {
+            let (ctx, events_loop) = Context::new(self.config)?;
+            Ok(Application::from_context(ctx, Some(events_loop), clock))
+        }
+    }
+}
+
+/// Collects pending window events. Engine-side state such as the DPI factor
+/// is updated right away; the returned input is left for the caller to
+/// dispatch, so it can be recorded or replaced by a replay. The flag is
+/// `true` once the window asked to close.
+fn poll_events(events_loop: &mut Option<EventsLoop>, ctx: &mut Context) -> (bool, Vec<InputEvent>) {
+    let mut closed = false;
+    let mut events = Vec::new();
+    if let Some(ref mut events_loop) = *events_loop {
+        events_loop.poll_events(|event| {
+            let input = match event {
+                winit::Event::WindowEvent { event: winit::WindowEvent::CloseRequested, .. } => {
+                    closed = true;
+                    None
+                },
+                winit::Event::WindowEvent { event, .. } => InputEvent::from_window_event(&event),
+                winit::Event::DeviceEvent { event, .. } => InputEvent::from_device_event(&event),
+                _ => None,
+            };
+
+            if let Some(input) = input {
+                match input {
+                    InputEvent::Resize { .. } => ctx.invalidate_swapchain(),
+                    InputEvent::HiDpiFactorChanged(factor) => {
+                        ctx.dpi_factor = factor;
+                        ctx.invalidate_swapchain();
+                    },
+                    _ => {},
+                }
+                events.push(input);
+            }
+
+        });
+    }
+
+    (closed, events)
+}
@@ -0,0 +1,58 @@
+extern crate sw3d;
+
+use std::sync::Arc;
+
+use sw3d::application::Application;
+use sw3d::event::EventHandler;
+use sw3d::render::Context;
+use sw3d::render::mesh::Mesh;
+use sw3d::render::material::Material;
+use sw3d::render::CUBE;
+
+
+pub struct State {
+    pub cube: Mesh,
+}
+
+impl State {
+    fn new(app: &mut Application) -> sw3d::Result<Self> {
+        let texture = app.ctx.textures.load("tex.png")?;
+        let material = Arc::new(Material::textured(texture));
+        let mut cube = Mesh::new(CUBE.to_vec(), app.ctx.device.clone(), app.ctx.queue.clone(), material)?;
+        cube.transform.scale(0.1);
+
+        Ok(Self {
+            cube,
+        })
+    }
+}
+
+impl EventHandler for State {
+    fn update(&mut self, _ctx: &mut Context, _dt: f32) {
+        self.cube.transform.translate(0.005, 0.0, 0.0);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _alpha: f32) {
+        ctx.draw(self.cube.clone());
+    }
+}
+
+fn run() -> sw3d::Result<()> {
+
+    let mut app = Application::new_headless(800, 800, "Headless")?;
+
+    let mut state = State::new(&mut app)?;
+    app.run_frames(&mut state, 60)?;
+
+    let image = app.ctx.read_image()?;
+    image.save("headless.png")?;
+
+    Ok(())
+}
+
+fn main () {
+    if let Err(err) = run() {
+        println!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -1,11 +1,13 @@
 extern crate sw3d;
 
-use sw3d::application::Application;
+use sw3d::application::{ Application, ApplicationBuilder };
 use sw3d::event::EventHandler;
 use sw3d::render::Context;
 use sw3d::render::mesh::Mesh;
-use sw3d::render::CUBE;
+use sw3d::render::material::{ Blend, Material, RenderState, Shading };
+use sw3d::render::{ CUBE, CUBE_VERTICES, CUBE_INDICES, InstanceData };
 use sw3d::time::Clock;
+use std::sync::Arc;
 use cgmath::{
     SquareMatrix,
     Matrix4,
@@ -13,6 +15,8 @@ use cgmath::{
 
 };
 use sw3d::cgmath;
+use sw3d::winit::{ VirtualKeyCode, ModifiersState };
+use sw3d::input::bindings::{ InputMap, Input };
 
 
 pub struct State {
@@ -20,6 +24,9 @@ pub struct State {
     pub cube2: Mesh,
     pub cube3: Mesh,
     pub clock: Clock,
+    pub bindings: InputMap,
+    pub floor: Vec<InstanceData>,
+    pub wireframe: bool,
 
     pub world: Matrix4<f32>,
     pub view:  Matrix4<f32>,
@@ -29,11 +36,27 @@ pub struct State {
 }
 
 impl State {
-    fn new(app: &Application) -> Self {
-        let mut cube1 = Mesh::new(CUBE.to_vec(), app.ctx.device.clone(), app.ctx.queue.clone(), "tex.png".into());
-        let mut cube2 = Mesh::new(CUBE.to_vec(), app.ctx.device.clone(), app.ctx.queue.clone(), "tex.png".into());
-        let mut cube3 = Mesh::new(CUBE.to_vec(), app.ctx.device.clone(), app.ctx.queue.clone(), "tex.png".into());
+    fn new(app: &mut Application) -> sw3d::Result<Self> {
+        let texture = app.ctx.textures.load("tex.png")?;
+        let material = Arc::new(Material::textured(texture.clone()));
+        let glass = Arc::new(Material::textured(texture)
+            .with_tint([0.4, 0.7, 1.0, 0.5])
+            .with_tiling(2.0, 2.0)
+            .with_render_state(RenderState { blend: Blend::Alpha, depth_write: false, .. RenderState::default() }));
+
+        let mut cube1 = Mesh::new(CUBE.to_vec(), app.ctx.device.clone(), app.ctx.queue.clone(), material)?;
+        let mut cube2 = cube1.with_material(glass);
+        let mut cube3 = Mesh::new_indexed(CUBE_VERTICES.to_vec(), CUBE_INDICES.to_vec(), app.ctx.device.clone(), app.ctx.queue.clone(),
+            Arc::new(Material::vertex_color()))?;
         let clock = Clock::default();
+        let bindings = InputMap::load("bindings.ron").unwrap_or_else(|err| {
+            println!("{}, using default bindings", err);
+            let mut bindings = InputMap::new();
+            bindings.bind_action("jump", Input::Key(VirtualKeyCode::Space));
+            bindings.bind_axis("move_up", Input::Key(VirtualKeyCode::W), 1.0);
+            bindings.bind_axis("move_up", Input::Key(VirtualKeyCode::S), -1.0);
+            bindings
+        });
         //cube.transform.translate(0.5, -0.5 , 0.0);
 
         cube2.transform.translate(2.0, 0.0, -3.0);
@@ -44,48 +67,104 @@ impl State {
         cube2.transform.scale(0.1);
         cube3.transform.scale(0.1);
 
+        let mut floor = Vec::new();
+        for x in -16 .. 16 {
+            for z in -16 .. 16 {
+                let model = Matrix4::from_scale(0.1) * Matrix4::from_translation(Vector3::new(x as f32, -4.0, z as f32 - 20.0));
+                let shade = if (x + z) % 2 == 0 { 1.0 } else { 0.6 };
+                floor.push(InstanceData::new(model, [shade, shade, shade, 1.0]));
+            }
+        }
+
         let world = Matrix4::identity();
         let view = Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0));
         let projection = cgmath::perspective(cgmath::Deg(45.0), 800.0/800.0, 0.1, 100.0);
 
-        Self {
+        Ok(Self {
             cube1,
             cube2,
             cube3,
             clock,
+            bindings,
+            floor,
+            wireframe: false,
             world,
             view,
             projection,
-        }
+        })
     }
 }
 
 impl EventHandler for State {
-    fn update(&mut self, _ctx: &mut Context) {
+    fn update(&mut self, ctx: &mut Context, _dt: f32) {
         self.clock.tick();
         let duration = self.clock.get_time_since_start();
         self.cube2.transform.translate(0.00, 0.0 , 0.005);
         self.cube1.transform.translate(0.005, -0.0 , 0.0);
         self.cube3.transform.translate(0.0, -0.005 , 0.0);
+
+        let move_up = self.bindings.axis(&ctx.input, "move_up");
+        self.cube1.transform.translate(0.0, 0.005 * move_up, 0.0);
+        if self.bindings.is_action_pressed(&ctx.input, "jump") {
+            self.cube1.transform.translate(0.0, 0.5, 0.0);
+        }
         //self.cube.transform.rotate_z(duration.into());
         //self.cube1.transform.rotate(10.0, [0.0, 0.0, 1.0]);
         
 
     }
 
-    fn draw(&mut self, ctx: &mut Context) {
+    fn draw(&mut self, ctx: &mut Context, _alpha: f32) {
         ctx.draw(self.cube1.clone());
         ctx.draw(self.cube2.clone());
-        ctx.draw(self.cube3.clone());
+        if self.wireframe {
+            ctx.draw_as(self.cube3.clone(), Shading::Wireframe);
+        } else {
+            ctx.draw(self.cube3.clone());
+        }
+        ctx.draw_instanced(self.cube1.clone(), self.floor.clone());
+    }
+
+    fn key_down_event(&mut self, ctx: &mut Context, key: VirtualKeyCode, _modifiers: ModifiersState) {
+        if key == VirtualKeyCode::F12 {
+            ctx.screenshot("screenshot.png");
+        }
+        if key == VirtualKeyCode::F1 {
+            self.wireframe = !self.wireframe;
+        }
     }
 }
 
-fn main () {
+fn run() -> sw3d::Result<()> {
+
+    let mut app = ApplicationBuilder::new("Voxel")
+        .dimensions(800, 800)
+        .vsync(true)
+        .samples(4)
+        .clear_color([0.1, 0.1, 0.1, 1.0])
+        .hot_reload(cfg!(debug_assertions))
+        .build()?;
+
+    let mut args = std::env::args().skip(1);
+    match (args.next(), args.next()) {
+        (Some(ref flag), Some(ref path)) if flag == "--record" => app.record(path)?,
+        (Some(ref flag), Some(ref path)) if flag == "--replay" => app.replay(path)?,
+        _ => (),
+    }
 
-    let mut app = Application::new(800, 800, "Voxel");
+    let mut state = State::new(&mut app)?;
+    app.run(&mut state)?;
 
-    let mut state = State::new(&app);
-    app.run(&mut state);
+    let stats = app.clock.stats();
+    println!("FPS: {:.1}, worst frame: {:?}, p99: {:?}", app.clock.get_fps(), stats.max(), stats.p99());
+    app.clock.profiler().save_csv("profile.csv")?;
 
+    Ok(())
+}
 
+fn main () {
+    if let Err(err) = run() {
+        println!("Error: {}", err);
+        std::process::exit(1);
+    }
 }
\ No newline at end of file
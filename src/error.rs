@@ -0,0 +1,165 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::result;
+
+use image;
+use notify;
+use ron;
+use vulkano;
+use vulkano_win;
+use input::bindings::BindingsError;
+
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// Everything that can go wrong while setting up or driving the renderer.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Image(image::ImageError),
+    Bindings(BindingsError),
+    Parse(ron::de::Error),
+    Serialize(ron::ser::Error),
+    Watch(notify::Error),
+    /// A GLSL file failed to compile at runtime, with the compiler output.
+    ShaderCompilation(PathBuf, String),
+    /// A shader is malformed or doesn't follow the engine's conventions.
+    InvalidShader(String),
+    /// A mesh has a number of indices that isn't a multiple of 3.
+    IndexCount(usize),
+    /// A mesh index, and the number of vertices it should be below.
+    IndexOutOfRange(u32, usize),
+    Instance(vulkano::instance::InstanceCreationError),
+    Layers(vulkano::instance::LayersListError),
+    DebugCallback(vulkano::instance::debug::DebugCallbackCreationError),
+    /// No physical device supports Vulkan.
+    NoDevice,
+    /// No usable device matches the one forced by name or index.
+    DeviceNotFound(String),
+    /// The device has no queue family able to draw (and present, when windowed).
+    NoQueueFamily,
+    Device(vulkano::device::DeviceCreationError),
+    Window(vulkano_win::CreationError),
+    SurfaceCapabilities(vulkano::swapchain::CapabilitiesError),
+    Swapchain(vulkano::swapchain::SwapchainCreationError),
+    AcquireImage(vulkano::swapchain::AcquireError),
+    RenderPass(vulkano::framebuffer::RenderPassCreationError),
+    Framebuffer(vulkano::framebuffer::FramebufferCreationError),
+    Pipeline(vulkano::pipeline::GraphicsPipelineCreationError),
+    ImageCreation(vulkano::image::ImageCreationError),
+    Sampler(vulkano::sampler::SamplerCreationError),
+    Memory(vulkano::memory::DeviceMemoryAllocError),
+    OutOfMemory(vulkano::OomError),
+    Flush(vulkano::sync::FlushError),
+    /// Recording or submitting a command buffer failed.
+    Command(Box<error::Error>),
+    /// `Context::read_image` was called on a windowed context.
+    NoOffscreenImage,
+    /// Images of this format can't be read back as RGBA.
+    UnsupportedFormat(vulkano::format::Format),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            Error::Image(ref err) => write!(f, "Failed to load image: {}", err),
+            Error::Bindings(ref err) => write!(f, "{}", err),
+            Error::Parse(ref err) => write!(f, "Failed to parse RON: {}", err),
+            Error::Serialize(ref err) => write!(f, "Failed to serialize RON: {}", err),
+            Error::Watch(ref err) => write!(f, "Failed to watch files: {}", err),
+            Error::ShaderCompilation(ref path, ref message) => write!(f, "Failed to compile {}:\n{}", path.display(), message),
+            Error::InvalidShader(ref message) => write!(f, "Invalid shader: {}", message),
+            Error::IndexCount(count) => write!(f, "{} indices don't make whole triangles", count),
+            Error::IndexOutOfRange(index, vertices) => write!(f, "Index {} is out of range for {} vertices", index, vertices),
+            Error::Instance(ref err) => write!(f, "Failed to create Vulkan instance: {}", err),
+            Error::Layers(ref err) => write!(f, "Failed to list Vulkan layers: {}", err),
+            Error::DebugCallback(ref err) => write!(f, "Failed to create debug callback: {}", err),
+            Error::NoDevice => write!(f, "No Vulkan device available"),
+            Error::DeviceNotFound(ref selector) => write!(f, "No usable Vulkan device matches \"{}\"", selector),
+            Error::NoQueueFamily => write!(f, "Failed to find a graphical queue family"),
+            Error::Device(ref err) => write!(f, "Failed to create device: {}", err),
+            Error::Window(ref err) => write!(f, "Failed to create window: {}", err),
+            Error::SurfaceCapabilities(ref err) => write!(f, "Failed to get surface capabilities: {}", err),
+            Error::Swapchain(ref err) => write!(f, "Failed to create swapchain: {}", err),
+            Error::AcquireImage(ref err) => write!(f, "Failed to acquire swapchain image: {}", err),
+            Error::RenderPass(ref err) => write!(f, "Failed to create render pass: {}", err),
+            Error::Framebuffer(ref err) => write!(f, "Failed to create framebuffer: {}", err),
+            Error::Pipeline(ref err) => write!(f, "Failed to create graphics pipeline: {}", err),
+            Error::ImageCreation(ref err) => write!(f, "Failed to create image: {}", err),
+            Error::Sampler(ref err) => write!(f, "Failed to create sampler: {}", err),
+            Error::Memory(ref err) => write!(f, "Failed to allocate device memory: {}", err),
+            Error::OutOfMemory(ref err) => write!(f, "Out of memory: {}", err),
+            Error::Flush(ref err) => write!(f, "Failed to submit frame: {}", err),
+            Error::Command(ref err) => write!(f, "Failed to record commands: {}", err),
+            Error::NoOffscreenImage => write!(f, "Context has no offscreen image to read back"),
+            Error::UnsupportedFormat(format) => write!(f, "Cannot read back images of format {:?}", format),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        "sw3d error"
+    }
+}
+
+macro_rules! impl_from_error {
+    ($variant:ident, $ty:ty) => {
+        impl From<$ty> for Error {
+            fn from(err: $ty) -> Self {
+                Error::$variant(err)
+            }
+        }
+    };
+}
+
+macro_rules! impl_from_command_error {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Error {
+                fn from(err: $ty) -> Self {
+                    Error::Command(Box::new(err))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_error!(Io, io::Error);
+impl_from_error!(Image, image::ImageError);
+impl_from_error!(Bindings, BindingsError);
+impl_from_error!(Parse, ron::de::Error);
+impl_from_error!(Serialize, ron::ser::Error);
+impl_from_error!(Watch, notify::Error);
+impl_from_error!(Instance, vulkano::instance::InstanceCreationError);
+impl_from_error!(Layers, vulkano::instance::LayersListError);
+impl_from_error!(DebugCallback, vulkano::instance::debug::DebugCallbackCreationError);
+impl_from_error!(Device, vulkano::device::DeviceCreationError);
+impl_from_error!(Window, vulkano_win::CreationError);
+impl_from_error!(SurfaceCapabilities, vulkano::swapchain::CapabilitiesError);
+impl_from_error!(Swapchain, vulkano::swapchain::SwapchainCreationError);
+impl_from_error!(AcquireImage, vulkano::swapchain::AcquireError);
+impl_from_error!(RenderPass, vulkano::framebuffer::RenderPassCreationError);
+impl_from_error!(Framebuffer, vulkano::framebuffer::FramebufferCreationError);
+impl_from_error!(Pipeline, vulkano::pipeline::GraphicsPipelineCreationError);
+impl_from_error!(ImageCreation, vulkano::image::ImageCreationError);
+impl_from_error!(Sampler, vulkano::sampler::SamplerCreationError);
+impl_from_error!(Memory, vulkano::memory::DeviceMemoryAllocError);
+impl_from_error!(OutOfMemory, vulkano::OomError);
+impl_from_error!(Flush, vulkano::sync::FlushError);
+
+impl_from_command_error!(
+    vulkano::command_buffer::BeginRenderPassError,
+    vulkano::command_buffer::DrawError,
+    vulkano::command_buffer::DrawIndexedError,
+    vulkano::command_buffer::AutoCommandBufferBuilderContextError,
+    vulkano::command_buffer::BuildError,
+    vulkano::command_buffer::CommandBufferExecError,
+    vulkano::command_buffer::CopyImageToBufferError,
+    vulkano::buffer::cpu_access::ReadLockError,
+    vulkano::descriptor::descriptor_set::PersistentDescriptorSetError,
+    vulkano::descriptor::descriptor_set::PersistentDescriptorSetBuildError
+);
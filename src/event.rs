@@ -1,13 +1,56 @@
 use render::Context;
-use winit::VirtualKeyCode;
+use input::InputEvent;
+use winit::{ VirtualKeyCode, ModifiersState, MouseButton };
 
 
 pub trait EventHandler {
-    fn update(&mut self, _ctx: &mut Context);
-    fn draw(&mut self, _ctx: &mut Context);
-    fn key_down_event(&mut self, _ctx: &mut Context, key: VirtualKeyCode) {
-        if key == VirtualKeyCode::W {
-            println!("fdp2");
+    /// Called once per fixed simulation step, `dt` being the step length in seconds.
+    fn update(&mut self, _ctx: &mut Context, _dt: f32);
+    /// Called once per rendered frame. `alpha` is how far the clock is
+    /// between the previous and the next simulation step.
+    fn draw(&mut self, _ctx: &mut Context, _alpha: f32);
+
+    fn key_down_event(&mut self, _ctx: &mut Context, _key: VirtualKeyCode, _modifiers: ModifiersState) {}
+
+    fn key_up_event(&mut self, _ctx: &mut Context, _key: VirtualKeyCode, _modifiers: ModifiersState) {}
+
+    /// Cursor position in logical pixels, relative to the top-left of the window.
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, _x: f64, _y: f64) {}
+
+    /// Unaccelerated mouse movement straight from the device, not clamped to the window.
+    fn raw_mouse_motion_event(&mut self, _ctx: &mut Context, _dx: f64, _dy: f64) {}
+
+    fn mouse_button_down_event(&mut self, _ctx: &mut Context, _button: MouseButton, _modifiers: ModifiersState) {}
+
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, _button: MouseButton, _modifiers: ModifiersState) {}
+
+    /// Scroll amount, in lines for wheels and in logical pixels for touchpads.
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, _y: f32) {}
+
+    fn text_input_event(&mut self, _ctx: &mut Context, _character: char) {}
+
+    fn focus_event(&mut self, _ctx: &mut Context, _gained: bool) {}
+
+    /// New window size in logical pixels.
+    fn resize_event(&mut self, _ctx: &mut Context, _width: f64, _height: f64) {}
+
+    fn hidpi_factor_changed_event(&mut self, _ctx: &mut Context, _factor: f64) {}
+
+    /// Entry point for every input event, live or replayed. The default
+    /// forwards each event to its dedicated hook above.
+    fn input_event(&mut self, ctx: &mut Context, event: &InputEvent) {
+        match *event {
+            InputEvent::KeyDown { key, modifiers } => self.key_down_event(ctx, key, modifiers),
+            InputEvent::KeyUp { key, modifiers } => self.key_up_event(ctx, key, modifiers),
+            InputEvent::MouseMotion { x, y } => self.mouse_motion_event(ctx, x, y),
+            InputEvent::RawMouseMotion { dx, dy } => self.raw_mouse_motion_event(ctx, dx, dy),
+            InputEvent::MouseButtonDown { button, modifiers } => self.mouse_button_down_event(ctx, button, modifiers),
+            InputEvent::MouseButtonUp { button, modifiers } => self.mouse_button_up_event(ctx, button, modifiers),
+            InputEvent::MouseWheel { x, y } => self.mouse_wheel_event(ctx, x, y),
+            InputEvent::Text(character) => self.text_input_event(ctx, character),
+            InputEvent::Focus(gained) => self.focus_event(ctx, gained),
+            InputEvent::Resize { width, height } => self.resize_event(ctx, width, height),
+            InputEvent::HiDpiFactorChanged(factor) => self.hidpi_factor_changed_event(ctx, factor),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{ Read, Write };
+use std::path::Path;
+
+use ron;
+use winit::{ VirtualKeyCode, MouseButton };
+use input::InputState;
+use input::remote::{ VirtualKeyCodeDef, MouseButtonDef };
+
+
+/// A physical input that can be bound to an action or an axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Input {
+    Key(#[serde(with = "VirtualKeyCodeDef")] VirtualKeyCode),
+    Mouse(#[serde(with = "MouseButtonDef")] MouseButton),
+    WheelUp,
+    WheelDown,
+    WheelLeft,
+    WheelRight,
+}
+
+impl Input {
+    fn is_down(&self, state: &InputState) -> bool {
+        match *self {
+            Input::Key(key) => state.is_down(key),
+            Input::Mouse(button) => state.is_mouse_down(button),
+            _ => self.wheel_amount(state) > 0.0,
+        }
+    }
+
+    fn is_pressed(&self, state: &InputState) -> bool {
+        match *self {
+            Input::Key(key) => state.is_pressed(key),
+            Input::Mouse(button) => state.is_mouse_pressed(button),
+            _ => self.wheel_amount(state) > 0.0,
+        }
+    }
+
+    fn is_released(&self, state: &InputState) -> bool {
+        match *self {
+            Input::Key(key) => state.is_released(key),
+            Input::Mouse(button) => state.is_mouse_released(button),
+            _ => false,
+        }
+    }
+
+    /// How far the wheel turned in this input's direction, `0.0` for keys and buttons.
+    fn wheel_amount(&self, state: &InputState) -> f32 {
+        let (x, y) = state.wheel_delta();
+        match *self {
+            Input::WheelUp => y.max(0.0),
+            Input::WheelDown => (-y).max(0.0),
+            Input::WheelRight => x.max(0.0),
+            Input::WheelLeft => (-x).max(0.0),
+            _ => 0.0,
+        }
+    }
+}
+
+/// One input contributing `scale` to an axis while it is held.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub input: Input,
+    pub scale: f32,
+}
+
+/// Maps physical inputs to named actions ("jump") and axes ("move_forward").
+///
+/// Bindings are stored as RON so they can be edited and reloaded without
+/// recompiling.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Input>>,
+    axes: BTreeMap<String, Vec<AxisBinding>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BindingsError> {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+        Self::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, BindingsError> {
+        Ok(ron::de::from_str(source)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BindingsError> {
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        File::create(path)?.write_all(source.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn bind_action(&mut self, action: &str, input: Input) {
+        let inputs = self.actions.entry(action.to_owned()).or_default();
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, input: Input, scale: f32) {
+        let bindings = self.axes.entry(axis.to_owned()).or_default();
+        bindings.retain(|binding| binding.input != input);
+        bindings.push(AxisBinding { input, scale });
+    }
+
+    /// Removes every binding of `action`.
+    pub fn unbind_action(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    /// Removes every binding of `axis`.
+    pub fn unbind_axis(&mut self, axis: &str) {
+        self.axes.remove(axis);
+    }
+
+    pub fn action_bindings(&self, action: &str) -> &[Input] {
+        self.actions.get(action).map(|inputs| inputs.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map(|bindings| bindings.as_slice()).unwrap_or(&[])
+    }
+
+    /// `true` while any input bound to `action` is held.
+    pub fn is_action_down(&self, state: &InputState, action: &str) -> bool {
+        self.action_bindings(action).iter().any(|input| input.is_down(state))
+    }
+
+    /// `true` on the step any input bound to `action` went down.
+    pub fn is_action_pressed(&self, state: &InputState, action: &str) -> bool {
+        self.action_bindings(action).iter().any(|input| input.is_pressed(state))
+    }
+
+    /// `true` on the step any input bound to `action` went up.
+    pub fn is_action_released(&self, state: &InputState, action: &str) -> bool {
+        self.action_bindings(action).iter().any(|input| input.is_released(state))
+    }
+
+    /// Current value of `axis`. Keys and buttons add up and are clamped to
+    /// `[-1, 1]`, so opposite keys cancel out; the wheel adds its scroll amount on top.
+    pub fn axis(&self, state: &InputState, axis: &str) -> f32 {
+        let mut digital: f32 = 0.0;
+        let mut wheel: f32 = 0.0;
+        for binding in self.axis_bindings(axis) {
+            match binding.input {
+                Input::Key(_) | Input::Mouse(_) => {
+                    if binding.input.is_down(state) {
+                        digital += binding.scale;
+                    }
+                },
+                _ => wheel += binding.input.wheel_amount(state) * binding.scale,
+            }
+        }
+
+        digital.clamp(-1.0, 1.0) + wheel
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Parse(ron::de::Error),
+    Serialize(ron::ser::Error),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindingsError::Io(ref err) => write!(f, "Failed to access bindings file: {}", err),
+            BindingsError::Parse(ref err) => write!(f, "Failed to parse bindings: {}", err),
+            BindingsError::Serialize(ref err) => write!(f, "Failed to serialize bindings: {}", err),
+        }
+    }
+}
+
+impl error::Error for BindingsError {
+    fn description(&self) -> &str {
+        match *self {
+            BindingsError::Io(_) => "failed to access bindings file",
+            BindingsError::Parse(_) => "failed to parse bindings",
+            BindingsError::Serialize(_) => "failed to serialize bindings",
+        }
+    }
+}
+
+impl From<io::Error> for BindingsError {
+    fn from(err: io::Error) -> Self {
+        BindingsError::Io(err)
+    }
+}
+
+impl From<ron::de::Error> for BindingsError {
+    fn from(err: ron::de::Error) -> Self {
+        BindingsError::Parse(err)
+    }
+}
+
+impl From<ron::ser::Error> for BindingsError {
+    fn from(err: ron::ser::Error) -> Self {
+        BindingsError::Serialize(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::ModifiersState;
+
+    fn movement() -> InputMap {
+        let mut map = InputMap::new();
+        map.bind_axis("move_right", Input::Key(VirtualKeyCode::D), 1.0);
+        map.bind_axis("move_right", Input::Key(VirtualKeyCode::Right), 1.0);
+        map.bind_axis("move_right", Input::Key(VirtualKeyCode::A), -1.0);
+        map
+    }
+
+    #[test]
+    fn digital_axis_is_clamped() {
+        let map = movement();
+        let mut state = InputState::new();
+        let modifiers = ModifiersState::default();
+
+        state.key_down(VirtualKeyCode::D, modifiers);
+        state.key_down(VirtualKeyCode::Right, modifiers);
+        assert_eq!(map.axis(&state, "move_right"), 1.0);
+    }
+
+    #[test]
+    fn opposing_bindings_cancel_out() {
+        let map = movement();
+        let mut state = InputState::new();
+        let modifiers = ModifiersState::default();
+
+        state.key_down(VirtualKeyCode::D, modifiers);
+        state.key_down(VirtualKeyCode::A, modifiers);
+        assert_eq!(map.axis(&state, "move_right"), 0.0);
+
+        // Two keys right and one left: clamped after summing, not before.
+        state.key_down(VirtualKeyCode::Right, modifiers);
+        assert_eq!(map.axis(&state, "move_right"), 1.0);
+
+        state.key_up(VirtualKeyCode::D, modifiers);
+        state.key_up(VirtualKeyCode::Right, modifiers);
+        assert_eq!(map.axis(&state, "move_right"), -1.0);
+    }
+
+    #[test]
+    fn unbound_axis_is_zero() {
+        let map = InputMap::new();
+        assert_eq!(map.axis(&InputState::new(), "move_right"), 0.0);
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let mut map = movement();
+        map.bind_action("fire", Input::Mouse(MouseButton::Left));
+        map.bind_action("fire", Input::Key(VirtualKeyCode::Space));
+
+        let source = ron::ser::to_string(&map).unwrap();
+        assert_eq!(InputMap::parse(&source).unwrap(), map);
+    }
+
+    #[test]
+    fn bad_ron_is_a_parse_error() {
+        match InputMap::parse("(actions: {\"fire\": [Key(NotAKey)]}, axes: {})") {
+            Err(BindingsError::Parse(_)) => (),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        match InputMap::parse("(actions: {") {
+            Err(BindingsError::Parse(_)) => (),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
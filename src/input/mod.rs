@@ -0,0 +1,332 @@
+pub mod bindings;
+pub mod replay;
+mod remote;
+
+use std::collections::HashSet;
+use winit::{
+    VirtualKeyCode,
+    MouseButton,
+    ModifiersState,
+    WindowEvent,
+    DeviceEvent,
+    ElementState,
+    KeyboardInput,
+    MouseScrollDelta,
+};
+use self::remote::{ VirtualKeyCodeDef, MouseButtonDef, ModifiersStateDef };
+
+
+/// Input as seen by the engine, translated from winit events.
+///
+/// Unlike winit events these can be serialized, which is what lets
+/// `replay::Recording` store and play them back.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    KeyDown {
+        #[serde(with = "VirtualKeyCodeDef")] key: VirtualKeyCode,
+        #[serde(with = "ModifiersStateDef")] modifiers: ModifiersState,
+    },
+    KeyUp {
+        #[serde(with = "VirtualKeyCodeDef")] key: VirtualKeyCode,
+        #[serde(with = "ModifiersStateDef")] modifiers: ModifiersState,
+    },
+    /// Cursor position in logical pixels.
+    MouseMotion { x: f64, y: f64 },
+    RawMouseMotion { dx: f64, dy: f64 },
+    MouseButtonDown {
+        #[serde(with = "MouseButtonDef")] button: MouseButton,
+        #[serde(with = "ModifiersStateDef")] modifiers: ModifiersState,
+    },
+    MouseButtonUp {
+        #[serde(with = "MouseButtonDef")] button: MouseButton,
+        #[serde(with = "ModifiersStateDef")] modifiers: ModifiersState,
+    },
+    MouseWheel { x: f32, y: f32 },
+    Text(char),
+    Focus(bool),
+    /// New window size in logical pixels.
+    Resize { width: f64, height: f64 },
+    HiDpiFactorChanged(f64),
+}
+
+impl InputEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match *event {
+            WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode: Some(key), modifiers, .. }, .. } => {
+                match state {
+                    ElementState::Pressed => Some(InputEvent::KeyDown { key, modifiers }),
+                    ElementState::Released => Some(InputEvent::KeyUp { key, modifiers }),
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => Some(InputEvent::MouseMotion { x: position.x, y: position.y }),
+            WindowEvent::MouseInput { state, button, modifiers, .. } => {
+                match state {
+                    ElementState::Pressed => Some(InputEvent::MouseButtonDown { button, modifiers }),
+                    ElementState::Released => Some(InputEvent::MouseButtonUp { button, modifiers }),
+                }
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(position) => (position.x as f32, position.y as f32),
+                };
+                Some(InputEvent::MouseWheel { x, y })
+            },
+            WindowEvent::ReceivedCharacter(character) => Some(InputEvent::Text(character)),
+            WindowEvent::Focused(gained) => Some(InputEvent::Focus(gained)),
+            WindowEvent::Resized(size) => Some(InputEvent::Resize { width: size.width, height: size.height }),
+            WindowEvent::HiDpiFactorChanged(factor) => Some(InputEvent::HiDpiFactorChanged(factor)),
+            _ => None,
+        }
+    }
+
+    pub fn from_device_event(event: &DeviceEvent) -> Option<Self> {
+        match *event {
+            DeviceEvent::MouseMotion { delta: (dx, dy) } => Some(InputEvent::RawMouseMotion { dx, dy }),
+            _ => None,
+        }
+    }
+}
+
+
+/// Snapshot of the keyboard and mouse, kept up to date by `Application::run`.
+///
+/// The "pressed" and "released" sets, the mouse delta and the wheel delta only
+/// hold what happened since the last simulation step, so they can be polled
+/// from `EventHandler::update` without missing or repeating an edge.
+pub struct InputState {
+    keys_down: HashSet<VirtualKeyCode>,
+    keys_pressed: HashSet<VirtualKeyCode>,
+    keys_released: HashSet<VirtualKeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    modifiers: ModifiersState,
+    mouse_position: (f64, f64),
+    mouse_delta: (f64, f64),
+    wheel_delta: (f32, f32),
+    focused: bool,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self {
+            keys_down: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+            buttons_down: HashSet::new(),
+            buttons_pressed: HashSet::new(),
+            buttons_released: HashSet::new(),
+            modifiers: ModifiersState::default(),
+            mouse_position: (0.0, 0.0),
+            mouse_delta: (0.0, 0.0),
+            wheel_delta: (0.0, 0.0),
+            focused: true,
+        }
+    }
+
+    pub fn is_down(&self, key: VirtualKeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// `true` only on the step the key went down.
+    pub fn is_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// `true` only on the step the key went up.
+    pub fn is_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn is_mouse_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Cursor position in logical pixels.
+    pub fn mouse_position(&self) -> (f64, f64) {
+        self.mouse_position
+    }
+
+    /// Raw mouse movement accumulated since the last step.
+    pub fn mouse_delta(&self) -> (f64, f64) {
+        self.mouse_delta
+    }
+
+    pub fn wheel_delta(&self) -> (f32, f32) {
+        self.wheel_delta
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Applies one input event to the snapshot.
+    pub fn process(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::KeyDown { key, modifiers } => self.key_down(key, modifiers),
+            InputEvent::KeyUp { key, modifiers } => self.key_up(key, modifiers),
+            InputEvent::MouseMotion { x, y } => self.mouse_moved(x, y),
+            InputEvent::RawMouseMotion { dx, dy } => self.raw_mouse_moved(dx, dy),
+            InputEvent::MouseButtonDown { button, modifiers } => self.mouse_button_down(button, modifiers),
+            InputEvent::MouseButtonUp { button, modifiers } => self.mouse_button_up(button, modifiers),
+            InputEvent::MouseWheel { x, y } => self.wheel_moved(x, y),
+            InputEvent::Focus(gained) => self.focus_changed(gained),
+            InputEvent::Text(_) | InputEvent::Resize { .. } | InputEvent::HiDpiFactorChanged(_) => (),
+        }
+    }
+
+    pub fn key_down(&mut self, key: VirtualKeyCode, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+        if self.keys_down.insert(key) {
+            self.keys_pressed.insert(key);
+        }
+    }
+
+    pub fn key_up(&mut self, key: VirtualKeyCode, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+        if self.keys_down.remove(&key) {
+            self.keys_released.insert(key);
+        }
+    }
+
+    pub fn mouse_button_down(&mut self, button: MouseButton, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+        if self.buttons_down.insert(button) {
+            self.buttons_pressed.insert(button);
+        }
+    }
+
+    pub fn mouse_button_up(&mut self, button: MouseButton, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+        if self.buttons_down.remove(&button) {
+            self.buttons_released.insert(button);
+        }
+    }
+
+    pub fn mouse_moved(&mut self, x: f64, y: f64) {
+        self.mouse_position = (x, y);
+    }
+
+    pub fn raw_mouse_moved(&mut self, dx: f64, dy: f64) {
+        self.mouse_delta.0 += dx;
+        self.mouse_delta.1 += dy;
+    }
+
+    pub fn wheel_moved(&mut self, x: f32, y: f32) {
+        self.wheel_delta.0 += x;
+        self.wheel_delta.1 += y;
+    }
+
+    /// Losing focus releases everything, as the matching "up" events
+    /// will be delivered to another window.
+    pub fn focus_changed(&mut self, gained: bool) {
+        self.focused = gained;
+        if !gained {
+            self.keys_released.extend(self.keys_down.drain());
+            self.buttons_released.extend(self.buttons_down.drain());
+        }
+    }
+
+    /// Clears the per-step edges and deltas. Called by `Application::run`
+    /// once they have been seen by at least one `update`.
+    pub fn end_step(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.wheel_delta = (0.0, 0.0);
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_edges_last_one_step() {
+        let mut input = InputState::new();
+        let modifiers = ModifiersState::default();
+
+        input.key_down(VirtualKeyCode::Space, modifiers);
+        // Key repeat doesn't press the key again.
+        input.end_step();
+        input.key_down(VirtualKeyCode::Space, modifiers);
+        assert!(input.is_down(VirtualKeyCode::Space));
+        assert!(!input.is_pressed(VirtualKeyCode::Space));
+
+        input.key_up(VirtualKeyCode::Space, modifiers);
+        assert!(!input.is_down(VirtualKeyCode::Space));
+        assert!(input.is_released(VirtualKeyCode::Space));
+
+        input.end_step();
+        assert!(!input.is_released(VirtualKeyCode::Space));
+    }
+
+    #[test]
+    fn press_and_release_within_a_step_are_both_seen() {
+        let mut input = InputState::new();
+        let modifiers = ModifiersState::default();
+
+        input.mouse_button_down(MouseButton::Left, modifiers);
+        input.mouse_button_up(MouseButton::Left, modifiers);
+        assert!(input.is_mouse_pressed(MouseButton::Left));
+        assert!(input.is_mouse_released(MouseButton::Left));
+        assert!(!input.is_mouse_down(MouseButton::Left));
+    }
+
+    #[test]
+    fn deltas_accumulate_until_the_step_ends() {
+        let mut input = InputState::new();
+
+        input.raw_mouse_moved(1.0, 2.0);
+        input.raw_mouse_moved(3.0, -1.0);
+        input.wheel_moved(0.0, 1.0);
+        input.wheel_moved(0.0, 2.0);
+        assert_eq!(input.mouse_delta(), (4.0, 1.0));
+        assert_eq!(input.wheel_delta(), (0.0, 3.0));
+
+        input.end_step();
+        assert_eq!(input.mouse_delta(), (0.0, 0.0));
+        assert_eq!(input.wheel_delta(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = InputState::new();
+        let modifiers = ModifiersState::default();
+
+        input.key_down(VirtualKeyCode::W, modifiers);
+        input.mouse_button_down(MouseButton::Right, modifiers);
+        input.end_step();
+
+        input.focus_changed(false);
+        assert!(!input.is_focused());
+        assert!(!input.is_down(VirtualKeyCode::W));
+        assert!(input.is_released(VirtualKeyCode::W));
+        assert!(!input.is_mouse_down(MouseButton::Right));
+        assert!(input.is_mouse_released(MouseButton::Right));
+
+        input.focus_changed(true);
+        assert!(input.is_focused());
+    }
+}
@@ -0,0 +1,287 @@
+use std::fs::File;
+use std::io::{ BufWriter, Read, Write };
+use std::path::Path;
+
+use ron;
+use error::Result;
+use input::InputEvent;
+
+
+/// Everything `Application` needs to replay one frame deterministically:
+/// the input received and how the simulation was stepped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub frame: usize,
+    /// Number of fixed updates run this frame.
+    pub steps: u32,
+    /// Length of each of those updates, in seconds.
+    pub dt: f32,
+    pub alpha: f32,
+    pub events: Vec<InputEvent>,
+}
+
+/// Input of a whole session, stored as RON.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub tick_rate: u32,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new(tick_rate: u32) -> Self {
+        Self {
+            tick_rate,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Also loads recordings a crash left unterminated, up to their last
+    /// complete frame.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut source = String::new();
+        File::open(path)?.read_to_string(&mut source)?;
+
+        match ron::de::from_str(&source) {
+            Ok(recording) => Ok(recording),
+            Err(err) => match Self::recover(&source) {
+                Some(recording) => {
+                    warn!("Recording is incomplete, loaded its first {} frames", recording.frames.len());
+                    Ok(recording)
+                },
+                None => Err(err.into()),
+            },
+        }
+    }
+
+    /// Reads the frames of an unterminated `Recorder` file, dropping the
+    /// last one if it was cut off. `None` if the header isn't there.
+    fn recover(source: &str) -> Option<Self> {
+        let mut lines = source.lines();
+        let header = lines.next()?;
+        if !header.starts_with(HEADER_START) || !header.ends_with(HEADER_END) {
+            return None;
+        }
+        let tick_rate = header[HEADER_START.len() .. header.len() - HEADER_END.len()].parse().ok()?;
+
+        let mut recording = Recording::new(tick_rate);
+        // A frame only spans several lines if an event holds a newline.
+        let mut pending = String::new();
+        for line in lines {
+            if pending.is_empty() && line == END {
+                break;
+            }
+            pending.push_str(line);
+            if let Ok(frame) = ron::de::from_str(pending.trim_end_matches(',')) {
+                recording.frames.push(frame);
+                pending.clear();
+            } else {
+                pending.push('\n');
+            }
+        }
+        Some(recording)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let source = ron::ser::to_string(self)?;
+        File::create(path)?.write_all(source.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Lines `Recorder` writes around the frames, one frame per line.
+const HEADER_START: &str = "(tick_rate:";
+const HEADER_END: &str = ",frames:[";
+const END: &str = "])";
+
+/// Writes a `Recording` to disk one frame at a time, so that the frames
+/// recorded before a crash are kept. The file is completed by `finish`,
+/// or when the recorder is dropped.
+pub struct Recorder {
+    file: BufWriter<File>,
+    finished: bool,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P, tick_rate: u32) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "{}{}{}", HEADER_START, tick_rate, HEADER_END)?;
+        file.flush()?;
+
+        Ok(Self {
+            file,
+            finished: false,
+        })
+    }
+
+    pub fn push(&mut self, frame: &RecordedFrame) -> Result<()> {
+        let source = ron::ser::to_string(frame)?;
+        writeln!(self.file, "{},", source)?;
+        self.file.flush()?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.close()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if !self.finished {
+            self.finished = true;
+            writeln!(self.file, "{}", END)?;
+            self.file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.close() {
+            error!("Failed to finish the recording: {}", err);
+        }
+    }
+}
+
+/// Hands out the frames of a recording one at a time.
+pub struct Playback {
+    recording: Recording,
+    cursor: usize,
+}
+
+impl Playback {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            cursor: 0,
+        }
+    }
+
+    pub fn tick_rate(&self) -> u32 {
+        self.recording.tick_rate
+    }
+
+    /// Returns `None` once every frame has been played.
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let frame = self.recording.frames.get(self.cursor).cloned();
+        self.cursor += 1;
+        frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.recording.frames.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+    use winit::{ ModifiersState, VirtualKeyCode };
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("sw3d-{}-{}.ron", name, process::id()))
+    }
+
+    fn frames() -> Vec<RecordedFrame> {
+        let modifiers = ModifiersState { shift: true, .. ModifiersState::default() };
+        vec![
+            RecordedFrame {
+                frame: 1,
+                steps: 2,
+                dt: 1.0 / 60.0,
+                alpha: 0.25,
+                events: vec![InputEvent::KeyDown { key: VirtualKeyCode::W, modifiers }],
+            },
+            RecordedFrame {
+                frame: 2,
+                steps: 0,
+                dt: 1.0 / 60.0,
+                alpha: 0.5,
+                events: vec![InputEvent::Text('\n'), InputEvent::MouseWheel { x: 0.0, y: -1.0 }],
+            },
+            RecordedFrame {
+                frame: 3,
+                steps: 1,
+                dt: 1.0 / 60.0,
+                alpha: 0.0,
+                events: vec![InputEvent::KeyUp { key: VirtualKeyCode::W, modifiers }],
+            },
+        ]
+    }
+
+    fn record(path: &Path, frames: &[RecordedFrame]) {
+        let mut recorder = Recorder::create(path, 60).unwrap();
+        for frame in frames {
+            recorder.push(frame).unwrap();
+        }
+        recorder.finish().unwrap();
+    }
+
+    #[test]
+    fn recorder_round_trip() {
+        let path = temp_path("round-trip");
+        record(&path, &frames());
+
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recording.tick_rate, 60);
+        assert_eq!(recording.frames, frames());
+    }
+
+    #[test]
+    fn dropped_recorder_completes_the_file() {
+        let path = temp_path("dropped");
+        {
+            let mut recorder = Recorder::create(&path, 30).unwrap();
+            recorder.push(&frames()[0]).unwrap();
+        }
+
+        let source = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let recording: Recording = ron::de::from_str(&source).unwrap();
+        assert_eq!(recording.tick_rate, 30);
+        assert_eq!(recording.frames, &frames()[.. 1]);
+    }
+
+    #[test]
+    fn file_cut_off_mid_frame_keeps_complete_frames() {
+        let path = temp_path("cut-off");
+        record(&path, &frames());
+
+        let source = fs::read_to_string(&path).unwrap();
+        let last_frame = source.find("(frame:3").unwrap();
+        fs::write(&path, &source[.. last_frame + 12]).unwrap();
+
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recording.tick_rate, 60);
+        assert_eq!(recording.frames, &frames()[.. 2]);
+    }
+
+    #[test]
+    fn file_cut_off_after_a_frame() {
+        let path = temp_path("cut-off-line");
+        record(&path, &frames());
+
+        let source = fs::read_to_string(&path).unwrap();
+        let end = source.rfind(END).unwrap();
+        fs::write(&path, &source[.. end]).unwrap();
+
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recording.frames, frames());
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        let path = temp_path("garbage");
+        fs::write(&path, "not a recording").unwrap();
+
+        let result = Recording::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
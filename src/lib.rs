@@ -2,12 +2,23 @@
 #[macro_use] extern crate vulkano_shader_derive;
 pub extern crate winit;
 extern crate vulkano_win;
-extern crate image;
+pub extern crate image;
 pub extern crate cgmath;
 extern crate glm;
+#[macro_use] extern crate log;
+extern crate ron;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+extern crate notify;
+extern crate glsl_to_spirv;
 
 
 pub mod render;
 pub mod application;
 pub mod event;
-pub mod time;
\ No newline at end of file
+pub mod state;
+pub mod time;
+pub mod input;
+pub mod error;
+
+pub use error::{ Error, Result };
\ No newline at end of file
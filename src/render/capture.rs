@@ -0,0 +1,53 @@
+use image::{ ImageBuffer, RgbaImage };
+use vulkano::format::Format;
+
+use error::{ Error, Result };
+
+
+/// Whether pixels of `format` can be turned into an `RgbaImage`.
+pub fn is_supported(format: Format) -> bool {
+    match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb |
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => true,
+        _ => false,
+    }
+}
+
+/// Converts raw pixels read back from an image of `format` to RGBA.
+///
+/// BGRA formats, common for swapchains, get their channels swapped. Bytes
+/// of sRGB formats are already gamma encoded, as PNG expects, and UNORM
+/// ones hold what is sent to the display, so neither needs converting.
+pub fn to_rgba(format: Format, width: u32, height: u32, mut data: Vec<u8>) -> Result<RgbaImage> {
+    match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => {},
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => {
+            for pixel in data.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        },
+        _ => return Err(Error::UnsupportedFormat(format)),
+    }
+
+    Ok(ImageBuffer::from_raw(width, height, data)
+        .expect("Readback buffer holds exactly one image"))
+}
+
+/// Mean absolute difference per channel between two images, from 0 for
+/// identical images to 1. `None` if their sizes differ.
+pub fn difference(a: &RgbaImage, b: &RgbaImage) -> Option<f64> {
+    if a.dimensions() != b.dimensions() {
+        return None;
+    }
+
+    let (a, b): (&[u8], &[u8]) = (&**a, &**b);
+    if a.is_empty() {
+        return Some(0.0);
+    }
+
+    let total: u64 = a.iter().zip(b.iter())
+        .map(|(&a, &b)| (i16::from(a) - i16::from(b)).abs() as u64)
+        .sum();
+
+    Some(total as f64 / (a.len() as f64 * 255.0))
+}
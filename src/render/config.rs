@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use vulkano::format::Format;
+use vulkano::swapchain::PresentMode;
+
+
+#[cfg(all(debug_assertions))]
+const ENABLE_VALIDATION_LAYERS: bool = true;
+#[cfg(not(debug_assertions))]
+const ENABLE_VALIDATION_LAYERS: bool = false;
+
+
+/// Window and renderer settings used to create a `Context`.
+/// Usually filled through `application::ApplicationBuilder`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub resizable: bool,
+    pub fullscreen: bool,
+    pub headless: bool,
+    /// Falls back to `PresentMode::Fifo`, which is always supported.
+    pub present_mode: PresentMode,
+    /// MSAA sample count of the main render pass, lowered to what the device
+    /// supports when the context is created.
+    pub samples: u32,
+    pub depth_format: Format,
+    /// How many frames the CPU may queue ahead of the GPU.
+    pub frames_in_flight: usize,
+    pub clear_color: [f32; 4],
+    pub validation: bool,
+    /// Forces a device by index, or by part of its name ignoring case.
+    /// The `SW3D_DEVICE` environment variable takes precedence.
+    /// Otherwise devices are scored, see `render::device::score`.
+    pub preferred_gpu: Option<String>,
+    /// Watch textures and shaders and reload them when they change on disk.
+    pub hot_reload: bool,
+    /// Where the GLSL sources live, only read when hot reloading.
+    pub shader_dir: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            title: "sw3d".into(),
+            width: 800,
+            height: 800,
+            resizable: true,
+            fullscreen: false,
+            headless: false,
+            present_mode: PresentMode::Fifo,
+            samples: 1,
+            depth_format: Format::D16Unorm,
+            frames_in_flight: 2,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            validation: ENABLE_VALIDATION_LAYERS,
+            preferred_gpu: None,
+            hot_reload: false,
+            shader_dir: PathBuf::from("src/shaders"),
+        }
+    }
+}
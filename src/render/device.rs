@@ -0,0 +1,108 @@
+use std::env;
+use std::sync::Arc;
+
+use vulkano::device::DeviceExtensions;
+use vulkano::instance::{ Instance, PhysicalDevice, PhysicalDeviceType };
+use vulkano::swapchain::Surface;
+use winit::Window;
+
+use error::{ Error, Result };
+
+
+/// Forces a device by index or name, overriding `Config::preferred_gpu`.
+pub const DEVICE_ENV_VAR: &str = "SW3D_DEVICE";
+
+/// How a device is forced: its index in enumeration order, or part of its
+/// name, compared ignoring case.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceSelector {
+    Index(usize),
+    Name(String),
+}
+
+impl DeviceSelector {
+    /// Numbers are read as indices, anything else as a name.
+    pub fn parse(selector: &str) -> Self {
+        match selector.trim().parse() {
+            Ok(index) => DeviceSelector::Index(index),
+            Err(_) => DeviceSelector::Name(selector.trim().to_lowercase()),
+        }
+    }
+
+    pub fn matches(&self, physical: PhysicalDevice) -> bool {
+        match *self {
+            DeviceSelector::Index(index) => physical.index() == index,
+            DeviceSelector::Name(ref name) => physical.name().to_lowercase().contains(name),
+        }
+    }
+}
+
+/// Ranks a device for rendering, `None` if it can't be used at all: it
+/// needs a graphics queue, and when a surface is given, a queue able to
+/// present to it and swapchain support.
+pub fn score(physical: PhysicalDevice, surface: Option<&Arc<Surface<Window>>>) -> Option<u32> {
+    let has_queue = physical.queue_families().any(|q| {
+        q.supports_graphics() && surface.map_or(true, |surface| surface.is_supported(q).unwrap_or(false))
+    });
+    if !has_queue {
+        return None;
+    }
+    if surface.is_some() && !DeviceExtensions::supported_by_device(physical).khr_swapchain {
+        return None;
+    }
+
+    let mut score = match physical.ty() {
+        PhysicalDeviceType::DiscreteGpu => 10_000,
+        PhysicalDeviceType::IntegratedGpu => 5_000,
+        PhysicalDeviceType::VirtualGpu => 2_000,
+        PhysicalDeviceType::Cpu => 1_000,
+        PhysicalDeviceType::Other => 0,
+    };
+
+    let features = physical.supported_features();
+    if features.sampler_anisotropy {
+        score += 100;
+    }
+    if features.fill_mode_non_solid {
+        score += 100;
+    }
+
+    // Breaks ties between devices of the same kind.
+    score += physical.limits().max_image_dimension_2d() / 1024;
+
+    Some(score)
+}
+
+/// Picks the device to render with. A device forced through `SW3D_DEVICE`
+/// or `preferred` must exist and be usable; otherwise the best scored
+/// device wins.
+pub fn select<'a>(instance: &'a Arc<Instance>, surface: Option<&Arc<Surface<Window>>>,
+                  preferred: Option<&str>) -> Result<PhysicalDevice<'a>> {
+
+    for physical in PhysicalDevice::enumerate(instance) {
+        debug!("Available device {}: {} ({:?}), score {:?}",
+            physical.index(), physical.name(), physical.ty(), score(physical, surface));
+    }
+
+    let forced = env::var(DEVICE_ENV_VAR).ok()
+        .filter(|selector| !selector.trim().is_empty())
+        .or_else(|| preferred.map(|selector| selector.to_owned()));
+
+    let physical = match forced {
+        Some(selector) => {
+            let parsed = DeviceSelector::parse(&selector);
+            PhysicalDevice::enumerate(instance)
+                .find(|&physical| parsed.matches(physical) && score(physical, surface).is_some())
+                .ok_or(Error::DeviceNotFound(selector))?
+        },
+        None => PhysicalDevice::enumerate(instance)
+            .filter_map(|physical| score(physical, surface).map(|score| (score, physical)))
+            .max_by_key(|&(score, _)| score)
+            .map(|(_, physical)| physical)
+            .ok_or(Error::NoDevice)?,
+    };
+
+    info!("Using device {}: {} ({:?})", physical.index(), physical.name(), physical.ty());
+
+    Ok(physical)
+}
@@ -0,0 +1,171 @@
+use std::mem;
+use std::ptr;
+
+use render::shader::{ PushConstants, ShaderId, MAX_PUSH_CONSTANTS_SIZE };
+use render::texture::TextureHandle;
+
+
+/// Which shaders a material is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shading {
+    /// Texture modulated by the base color and tint.
+    Textured,
+    /// Base color and tint only, the texture is ignored.
+    FlatColor,
+    /// `Vertex::color` modulated by the base color and tint.
+    VertexColor,
+    /// Triangle edges in the base color and tint. Drawn filled on devices
+    /// without `fill_mode_non_solid`.
+    Wireframe,
+    /// User shaders registered with `PipelineCache::add_custom`.
+    Custom(ShaderId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Blend {
+    Opaque,
+    /// Blended with what's behind using the output alpha. Drawn after the
+    /// opaque meshes, in submission order.
+    Alpha,
+    Additive,
+}
+
+/// Fixed-function state baked into the pipeline of a material.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderState {
+    pub cull_mode: CullMode,
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub blend: Blend,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            cull_mode: CullMode::None,
+            depth_test: true,
+            depth_write: true,
+            blend: Blend::Opaque,
+        }
+    }
+}
+
+/// Uniform parameters of a material, laid out like the `Material` block of
+/// the fragment shaders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct MaterialParams {
+    pub base_color: [f32; 4],
+    pub tint: [f32; 4],
+    /// How many times the texture repeats across the UVs.
+    pub tiling: [f32; 2],
+    pub offset: [f32; 2],
+}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            tint: [1.0, 1.0, 1.0, 1.0],
+            tiling: [1.0, 1.0],
+            offset: [0.0, 0.0],
+        }
+    }
+}
+
+/// How a mesh looks: shaders, textures, parameters and render state.
+/// Meshes share materials through an `Arc`, so one piece of geometry can
+/// be drawn with several materials and one material used by many meshes.
+#[derive(Clone)]
+pub struct Material {
+    pub shading: Shading,
+    /// Sampled by textured shading. Without one, a white texture is bound.
+    pub texture: Option<TextureHandle>,
+    pub params: MaterialParams,
+    pub render_state: RenderState,
+    /// Pushed before each draw, for custom shaders that declare push constants.
+    pub push_constants: PushConstants,
+}
+
+impl Material {
+    pub fn new(shading: Shading) -> Self {
+        Self {
+            shading,
+            texture: None,
+            params: MaterialParams::default(),
+            render_state: RenderState::default(),
+            push_constants: [0; MAX_PUSH_CONSTANTS_SIZE / 4],
+        }
+    }
+
+    pub fn textured(texture: TextureHandle) -> Self {
+        Self {
+            texture: Some(texture),
+            .. Self::new(Shading::Textured)
+        }
+    }
+
+    pub fn flat_color(color: [f32; 4]) -> Self {
+        Self::new(Shading::FlatColor).with_base_color(color)
+    }
+
+    pub fn vertex_color() -> Self {
+        Self::new(Shading::VertexColor)
+    }
+
+    pub fn wireframe(color: [f32; 4]) -> Self {
+        Self::new(Shading::Wireframe).with_base_color(color)
+    }
+
+    pub fn with_shading(mut self, shading: Shading) -> Self {
+        self.shading = shading;
+        self
+    }
+
+    pub fn with_base_color(mut self, base_color: [f32; 4]) -> Self {
+        self.params.base_color = base_color;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Self {
+        self.params.tint = tint;
+        self
+    }
+
+    pub fn with_tiling(mut self, x: f32, y: f32) -> Self {
+        self.params.tiling = [x, y];
+        self
+    }
+
+    pub fn with_render_state(mut self, render_state: RenderState) -> Self {
+        self.render_state = render_state;
+        self
+    }
+
+    /// Copies `data` into the push constants, laid out as the shader's
+    /// push constant block.
+    ///
+    /// # Panics
+    /// If `T` is bigger than `MAX_PUSH_CONSTANTS_SIZE`.
+    pub fn with_push_constants<T: Copy>(mut self, data: T) -> Self {
+        let size = mem::size_of::<T>();
+        assert!(size <= MAX_PUSH_CONSTANTS_SIZE, "push constants are limited to {} bytes", MAX_PUSH_CONSTANTS_SIZE);
+
+        self.push_constants = [0; MAX_PUSH_CONSTANTS_SIZE / 4];
+        unsafe {
+            ptr::copy_nonoverlapping(&data as *const T as *const u8, self.push_constants.as_mut_ptr() as *mut u8, size);
+        }
+        self
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.render_state.blend != Blend::Opaque
+    }
+}
@@ -1,16 +1,19 @@
 
 //use std;
-use std::sync::Arc;
+use std::sync::{ Arc, Weak };
 use std::path::Path;
 //use vulkano::pipeline::{ GraphicsPipeline, vertex::SingleBufferDefinition};
-use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage};
+use vulkano::buffer::{CpuAccessibleBuffer, BufferSlice, BufferUsage, ImmutableBuffer, TypedBufferAccess};
+use vulkano::sync::GpuFuture;
 use vulkano::device::{ Device, Queue };
 use render::Vertex;
 use image;
 use vulkano;
 use render;
 use render::transform::Transform;
+use render::material::Material;
 use render::vs;
+use error::{ Error, Result };
 use cgmath::{
     SquareMatrix,
     Matrix4,
@@ -20,56 +23,260 @@ use cgmath::{
 //type ConcreteGraphicsPipeline = GraphicsPipeline<SingleBufferDefinition<Vertex>, std::boxed::Box<vulkano::descriptor::PipelineLayoutAbstract + std::marker::Send + std::marker::Sync>, std::sync::Arc<vulkano::framebuffer::RenderPassAbstract + std::marker::Send + std::marker::Sync>>;
 //type ConcreteDescriptor = vulkano::descriptor::descriptor_set::PersistentDescriptorSet<std::sync::Arc<vulkano::pipeline::GraphicsPipeline<vulkano::pipeline::vertex::SingleBufferDefinition<render::Vertex>, std::boxed::Box<vulkano::descriptor::PipelineLayoutAbstract + std::marker::Send + std::marker::Sync>, std::sync::Arc<vulkano::framebuffer::RenderPassAbstract + std::marker::Send + std::marker::Sync>>>, (((), vulkano::descriptor::descriptor_set::PersistentDescriptorSetImg<std::sync::Arc<vulkano::image::ImmutableImage<vulkano::format::R8G8B8A8Srgb>>>), vulkano::descriptor::descriptor_set::PersistentDescriptorSetSampler)>;
 
-#[derive(Clone)]
-pub struct Mesh {
-    pub vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
-    pub texture: Arc<vulkano::image::ImmutableImage<vulkano::format::R8G8B8A8Srgb>>,
-    pub sampler: Arc<vulkano::sampler::Sampler>,
-    pub transform: Transform,
+pub type VertexBuffer = Arc<TypedBufferAccess<Content = [Vertex]> + Send + Sync>;
 
+/// Where a mesh's geometry lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshUsage {
+    /// Uploaded once through a staging buffer into device-local memory,
+    /// the fastest to draw from.
+    Static,
+    /// Kept in host-visible memory, cheap to replace every frame.
+    Dynamic,
 }
 
-impl Mesh {
-    pub fn new(data: Vec<Vertex>, device: Arc<Device>, queue: Arc<Queue>, path: String) -> Self {
+/// Creates a buffer holding `data` as `usage` asks for. Static uploads
+/// are waited on before returning.
+fn create_buffer<T>(data: Vec<T>, buffer_usage: BufferUsage, usage: MeshUsage, device: Arc<Device>,
+                    queue: Arc<Queue>) -> Result<Arc<TypedBufferAccess<Content = [T]> + Send + Sync>>
+    where T: Send + Sync + 'static
+{
+    match usage {
+        MeshUsage::Static => {
+            let (buffer, upload) = ImmutableBuffer::from_iter(data.into_iter(), buffer_usage, queue)?;
+            upload.then_signal_fence_and_flush()?.wait(None)?;
+            Ok(buffer)
+        },
+        MeshUsage::Dynamic => Ok(CpuAccessibleBuffer::from_iter(device, buffer_usage, data.into_iter())?),
+    }
+}
+
+/// How many buffers of each kind a dynamic mesh cycles through, enough for
+/// the frames in flight to keep theirs while a new one is written.
+const MAX_DYNAMIC_BUFFERS: usize = 4;
+
+type RingSlice<T> = BufferSlice<[T], Arc<CpuAccessibleBuffer<[T]>>>;
+
+/// Host-visible buffers `Mesh::set_geometry` cycles through. New geometry
+/// is written in place into one that nothing draws from any more, so that
+/// replacing it every frame doesn't allocate.
+struct BufferRing<T> {
+    /// Each buffer with the handle to it last handed out. Meshes, their
+    /// clones, queued draws and frames in flight all share that handle, so
+    /// the buffer is free once it is gone.
+    buffers: Vec<(Arc<CpuAccessibleBuffer<[T]>>, Weak<RingSlice<T>>)>,
+}
+
+impl<T> BufferRing<T> where T: Clone + Send + Sync + 'static {
+    fn new() -> Self {
+        Self {
+            buffers: Vec::new(),
+        }
+    }
+
+    /// Returns the part of the buffer `data` ended up in. A new buffer is
+    /// only allocated when none is free and large enough.
+    fn write(&mut self, data: &[T], usage: BufferUsage, device: Arc<Device>) -> Result<Arc<RingSlice<T>>> {
+        for (buffer, handle) in self.buffers.iter_mut() {
+            if buffer.len() < data.len() || handle.upgrade().is_some() {
+                continue;
+            }
+
+            if let Ok(mut content) = buffer.write() {
+                content[.. data.len()].clone_from_slice(data);
+                let slice = Arc::new(Self::slice(buffer.clone(), data.len()));
+                *handle = Arc::downgrade(&slice);
+                return Ok(slice);
+            }
+        }
+
+        if self.buffers.len() >= MAX_DYNAMIC_BUFFERS {
+            // Forget a free buffer that was too small if there is one, the
+            // oldest otherwise. Whatever still draws from it keeps it alive.
+            let index = self.buffers.iter()
+                .position(|(_, handle)| handle.upgrade().is_none())
+                .unwrap_or(0);
+            self.buffers.remove(index);
+        }
+
+        let buffer = CpuAccessibleBuffer::from_iter(device, usage, data.iter().cloned())?;
+        let slice = Arc::new(Self::slice(buffer.clone(), data.len()));
+        self.buffers.push((buffer, Arc::downgrade(&slice)));
+        Ok(slice)
+    }
+
+    fn slice(buffer: Arc<CpuAccessibleBuffer<[T]>>, len: usize) -> RingSlice<T> {
+        BufferSlice::from_typed_buffer_access(buffer)
+            .slice(0 .. len)
+            .expect("Data fits in the buffer")
+    }
+}
+
+/// Buffers of a `MeshUsage::Dynamic` mesh.
+struct DynamicBuffers {
+    vertices: BufferRing<Vertex>,
+    u16_indices: BufferRing<u16>,
+    u32_indices: BufferRing<u32>,
+}
+
+impl DynamicBuffers {
+    fn new() -> Self {
+        Self {
+            vertices: BufferRing::new(),
+            u16_indices: BufferRing::new(),
+            u32_indices: BufferRing::new(),
+        }
+    }
 
-        let vertex_buffer = CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(),
-            data
-                .iter()
-                .cloned())
-            .expect("Failed to create buffer");
+    fn write(&mut self, data: &[Vertex], indices: Option<&Indices>, device: Arc<Device>) -> Result<(VertexBuffer, Option<IndexBuffer>)> {
+        let vertex_buffer: VertexBuffer = self.vertices.write(data, BufferUsage::vertex_buffer(), device.clone())?;
+        let index_buffer = match indices {
+            Some(&Indices::U16(ref indices)) => Some(IndexBuffer::U16(
+                self.u16_indices.write(indices, BufferUsage::index_buffer(), device)?)),
+            Some(&Indices::U32(ref indices)) => Some(IndexBuffer::U32(
+                self.u32_indices.write(indices, BufferUsage::index_buffer(), device)?)),
+            None => None,
+        };
 
-        let (texture, _tex_future) = {
-            //let image = image::load_from_memory_with_format(include_bytes!("test"),image::ImageFormat::PNG).unwrap().to_rgba();
+        Ok((vertex_buffer, index_buffer))
+    }
+}
 
-            let image = image::open(&Path::new(&path)).unwrap().to_rgba();
-            let (width, height) = image.dimensions();
+/// A clone starts without buffers of its own: two rings sharing a buffer
+/// could each find it free and write over the other's geometry.
+impl Clone for DynamicBuffers {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
 
-            let image_data = image.into_raw().clone();
+/// Indices into a mesh's vertices, three per triangle.
+#[derive(Debug, Clone)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
 
-            vulkano::image::immutable::ImmutableImage::from_iter(
-                image_data.iter().cloned(),
-                vulkano::image::Dimensions::Dim2d { width, height },
-                vulkano::format::R8G8B8A8Srgb,
-                queue.clone()).unwrap()
+impl Indices {
+    pub fn len(&self) -> usize {
+        match *self {
+            Indices::U16(ref indices) => indices.len(),
+            Indices::U32(ref indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Checks that the indices make whole triangles out of `vertices` vertices.
+    pub fn validate(&self, vertices: usize) -> Result<()> {
+        if self.len() % 3 != 0 {
+            return Err(Error::IndexCount(self.len()));
+        }
+
+        let max = match *self {
+            Indices::U16(ref indices) => indices.iter().cloned().max().map(u32::from),
+            Indices::U32(ref indices) => indices.iter().cloned().max(),
         };
+        match max {
+            Some(index) if index as usize >= vertices => Err(Error::IndexOutOfRange(index, vertices)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<Vec<u16>> for Indices {
+    fn from(indices: Vec<u16>) -> Self {
+        Indices::U16(indices)
+    }
+}
 
+impl From<Vec<u32>> for Indices {
+    fn from(indices: Vec<u32>) -> Self {
+        Indices::U32(indices)
+    }
+}
 
-        let sampler = vulkano::sampler::Sampler::new(device.clone(), vulkano::sampler::Filter::Linear,
-                                                    vulkano::sampler::Filter::Linear, vulkano::sampler::MipmapMode::Nearest,
-                                                    vulkano::sampler::SamplerAddressMode::Repeat,
-                                                    vulkano::sampler::SamplerAddressMode::Repeat,
-                                                    vulkano::sampler::SamplerAddressMode::Repeat,
-                                                    0.0, 1.0, 0.0, 0.0).unwrap();
+#[derive(Clone)]
+pub enum IndexBuffer {
+    U16(Arc<TypedBufferAccess<Content = [u16]> + Send + Sync>),
+    U32(Arc<TypedBufferAccess<Content = [u32]> + Send + Sync>),
+}
+
+impl IndexBuffer {
+    /// The indices aren't checked, see `Indices::validate`.
+    pub fn new(indices: Indices, usage: MeshUsage, device: Arc<Device>, queue: Arc<Queue>) -> Result<Self> {
+        Ok(match indices {
+            Indices::U16(indices) => IndexBuffer::U16(
+                create_buffer(indices, BufferUsage::index_buffer(), usage, device, queue)?),
+            Indices::U32(indices) => IndexBuffer::U32(
+                create_buffer(indices, BufferUsage::index_buffer(), usage, device, queue)?),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct Mesh {
+    pub vertex_buffer: VertexBuffer,
+    /// When set, the mesh is drawn with `draw_indexed`.
+    pub index_buffer: Option<IndexBuffer>,
+    pub material: Arc<Material>,
+    pub transform: Transform,
+    pub usage: MeshUsage,
+    dynamic: DynamicBuffers,
+}
+
+impl Mesh {
+    /// Creates a static mesh, see `with_usage`.
+    pub fn new(data: Vec<Vertex>, device: Arc<Device>, queue: Arc<Queue>, material: Arc<Material>) -> Result<Self> {
+        Self::with_usage(data, None, MeshUsage::Static, device, queue, material)
+    }
+
+    /// Creates a static mesh drawn through an index buffer, e.g. from
+    /// `CUBE_VERTICES` and `CUBE_INDICES`.
+    pub fn new_indexed<I: Into<Indices>>(data: Vec<Vertex>, indices: I, device: Arc<Device>, queue: Arc<Queue>, material: Arc<Material>) -> Result<Self> {
+        Self::with_usage(data, Some(indices.into()), MeshUsage::Static, device, queue, material)
+    }
+
+    /// Use `MeshUsage::Dynamic` for geometry replaced often with `set_geometry`.
+    /// Fails if the indices don't make whole triangles out of `data`.
+    pub fn with_usage(data: Vec<Vertex>, indices: Option<Indices>, usage: MeshUsage,
+                      device: Arc<Device>, queue: Arc<Queue>, material: Arc<Material>) -> Result<Self> {
+        let mut dynamic = DynamicBuffers::new();
+        let (vertex_buffer, index_buffer) = create_geometry(data, indices, usage, &mut dynamic, device, queue)?;
 
         let transform = Transform::new();
-        Self {
+        Ok(Self {
             vertex_buffer,
-            texture,
-            sampler,
+            index_buffer,
+            material,
             transform,
+            usage,
+            dynamic,
+        })
+    }
+
+    /// Draws the same geometry with another material.
+    pub fn with_material(&self, material: Arc<Material>) -> Self {
+        Self {
+            material,
+            .. self.clone()
         }
     }
 
+    /// Replaces the geometry, checking the indices as `with_usage` does.
+    /// Frames still in flight keep drawing the old one.
+    ///
+    /// Static meshes get new buffers. Dynamic meshes write to one of their
+    /// previous buffers nothing draws from any more, and only allocate when
+    /// the geometry outgrows them.
+    pub fn set_geometry(&mut self, data: Vec<Vertex>, indices: Option<Indices>, device: Arc<Device>, queue: Arc<Queue>) -> Result<()> {
+        let (vertex_buffer, index_buffer) = create_geometry(data, indices, self.usage, &mut self.dynamic, device, queue)?;
+        self.vertex_buffer = vertex_buffer;
+        self.index_buffer = index_buffer;
+        Ok(())
+    }
 
     pub fn update(&self, view: [[f32; 4]; 4], projection: [[f32; 4]; 4],  world: [[f32; 4]; 4] ) -> render::vs::ty::Data {
 
@@ -78,7 +285,7 @@ impl Mesh {
 
             
 
-            let world = self.transform.scale * self.transform.translation_matrix();
+            let world = self.transform.model_matrix();
             let translation: [[f32; 4]; 4] = world.into();
 
             let vieww: Matrix4<f32> = view.into();
@@ -95,8 +302,172 @@ impl Mesh {
 
 }
 
+/// Creates the buffers of new geometry, going through `dynamic` for
+/// dynamic meshes.
+fn create_geometry(data: Vec<Vertex>, indices: Option<Indices>, usage: MeshUsage, dynamic: &mut DynamicBuffers,
+                   device: Arc<Device>, queue: Arc<Queue>) -> Result<(VertexBuffer, Option<IndexBuffer>)> {
+    if let Some(ref indices) = indices {
+        indices.validate(data.len())?;
+    }
+
+    match usage {
+        MeshUsage::Static => {
+            let vertex_buffer = create_buffer(data, BufferUsage::vertex_buffer(), usage, device.clone(), queue.clone())?;
+            let index_buffer = match indices {
+                Some(indices) => Some(IndexBuffer::new(indices, usage, device, queue)?),
+                None => None,
+            };
+            Ok((vertex_buffer, index_buffer))
+        },
+        MeshUsage::Dynamic => dynamic.write(&data, indices.as_ref(), device),
+    }
+}
+
+/// Loads an image file into a texture the shaders can sample.
+pub fn load_texture(path: &Path, queue: Arc<Queue>) -> Result<Arc<vulkano::image::ImmutableImage<vulkano::format::R8G8B8A8Srgb>>> {
+    //let image = image::load_from_memory_with_format(include_bytes!("test"),image::ImageFormat::PNG).unwrap().to_rgba();
+
+    let image = image::open(path)?.to_rgba();
+    let (width, height) = image.dimensions();
+
+    create_texture(image.into_raw(), width, height, queue)
+}
+
+/// Uploads RGBA pixels, row by row, into a texture.
+pub fn create_texture(data: Vec<u8>, width: u32, height: u32, queue: Arc<Queue>) -> Result<Arc<vulkano::image::ImmutableImage<vulkano::format::R8G8B8A8Srgb>>> {
+    let (texture, _tex_future) = vulkano::image::immutable::ImmutableImage::from_iter(
+        data.into_iter(),
+        vulkano::image::Dimensions::Dim2d { width, height },
+        vulkano::format::R8G8B8A8Srgb,
+        queue)?;
+
+    Ok(texture)
+}
+
 /*
 impl Clone for Mesh {
     fn clone(&self) -> Mesh { *self }
 }
-*/
\ No newline at end of file
+*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::Context;
+    use render::config::Config;
+
+    /// Needs a Vulkan driver; a CPU one such as lavapipe is enough.
+    fn device() -> Arc<Device> {
+        let ctx = Context::new_headless(Config {
+            width: 4,
+            height: 4,
+            headless: true,
+            .. Config::default()
+        }).expect("buffer tests need a Vulkan driver");
+        ctx.device.clone()
+    }
+
+    fn contents(slice: &RingSlice<u32>) -> Vec<u32> {
+        slice.buffer().read().unwrap()[.. slice.len()].to_vec()
+    }
+
+    #[test]
+    fn ring_reuses_buffers_nothing_draws_from() {
+        let device = device();
+        let mut ring = BufferRing::new();
+
+        let first = ring.write(&[1u32, 2, 3], BufferUsage::index_buffer(), device.clone()).unwrap();
+        let second = ring.write(&[4, 5, 6], BufferUsage::index_buffer(), device.clone()).unwrap();
+        assert_eq!(ring.buffers.len(), 2);
+        drop(first);
+
+        let third = ring.write(&[7, 8], BufferUsage::index_buffer(), device.clone()).unwrap();
+        assert_eq!(ring.buffers.len(), 2);
+        assert_eq!(contents(&second), [4, 5, 6]);
+        assert_eq!(contents(&third), [7, 8]);
+    }
+
+    #[test]
+    fn clone_keeps_its_geometry_when_the_original_is_replaced() {
+        let device = device();
+        let mut ring = BufferRing::new();
+
+        // A mesh's geometry, shared by a clone of the mesh.
+        let original = ring.write(&[1u32, 2, 3], BufferUsage::index_buffer(), device.clone()).unwrap();
+        let clone = original.clone();
+
+        // The original mesh replaces its geometry twice.
+        drop(original);
+        let replaced = ring.write(&[4, 5, 6], BufferUsage::index_buffer(), device.clone()).unwrap();
+        drop(replaced);
+        let replaced = ring.write(&[7, 8, 9], BufferUsage::index_buffer(), device.clone()).unwrap();
+
+        assert_eq!(contents(&clone), [1, 2, 3]);
+        assert_eq!(contents(&replaced), [7, 8, 9]);
+    }
+
+    #[test]
+    fn ring_allocates_for_larger_geometry() {
+        let device = device();
+        let mut ring = BufferRing::new();
+
+        drop(ring.write(&[1u32, 2, 3], BufferUsage::index_buffer(), device.clone()).unwrap());
+        let larger = ring.write(&[1, 2, 3, 4, 5, 6], BufferUsage::index_buffer(), device.clone()).unwrap();
+        assert_eq!(ring.buffers.len(), 2);
+        assert_eq!(contents(&larger), [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn ring_forgets_buffers_beyond_its_capacity() {
+        let device = device();
+        let mut ring = BufferRing::new();
+
+        let held: Vec<_> = (0 .. MAX_DYNAMIC_BUFFERS as u32 + 2)
+            .map(|value| ring.write(&[value], BufferUsage::index_buffer(), device.clone()).unwrap())
+            .collect();
+        assert_eq!(ring.buffers.len(), MAX_DYNAMIC_BUFFERS);
+        for (value, slice) in held.iter().enumerate() {
+            assert_eq!(contents(slice), [value as u32]);
+        }
+    }
+
+    #[test]
+    fn cloned_dynamic_buffers_start_empty() {
+        let device = device();
+        let mut buffers = DynamicBuffers::new();
+        let _held = buffers.u32_indices.write(&[0, 1, 2], BufferUsage::index_buffer(), device).unwrap();
+        assert_eq!(buffers.u32_indices.buffers.len(), 1);
+        assert!(buffers.clone().u32_indices.buffers.is_empty());
+    }
+
+    #[test]
+    fn whole_triangles_in_range_are_valid() {
+        assert!(Indices::from(vec![0u16, 1, 2, 2, 1, 3]).validate(4).is_ok());
+        assert!(Indices::from(vec![0u32, 1, 2]).validate(3).is_ok());
+        assert!(Indices::from(Vec::<u16>::new()).validate(0).is_ok());
+    }
+
+    #[test]
+    fn index_count_must_be_a_multiple_of_3() {
+        match Indices::from(vec![0u16, 1, 2, 3]).validate(4) {
+            Err(Error::IndexCount(4)) => (),
+            other => panic!("expected IndexCount(4), got {:?}", other),
+        }
+        match Indices::from(vec![0u32, 1]).validate(4) {
+            Err(Error::IndexCount(2)) => (),
+            other => panic!("expected IndexCount(2), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn indices_must_be_below_the_vertex_count() {
+        match Indices::from(vec![0u16, 1, 3]).validate(3) {
+            Err(Error::IndexOutOfRange(3, 3)) => (),
+            other => panic!("expected IndexOutOfRange(3, 3), got {:?}", other),
+        }
+        match Indices::from(vec![0u32, 1, 2, 70_000, 1, 2]).validate(65_536) {
+            Err(Error::IndexOutOfRange(70_000, 65_536)) => (),
+            other => panic!("expected IndexOutOfRange(70000, 65536), got {:?}", other),
+        }
+    }
+}
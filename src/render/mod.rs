@@ -1,9 +1,19 @@
 pub mod mesh;
 pub mod transform;
+pub mod config;
+pub mod reload;
+pub mod capture;
+pub mod device;
+pub mod texture;
+pub mod material;
+pub mod pipeline;
+pub mod spirv;
+pub mod shader;
 
 
 use std::sync::Arc;
-use std;
+use std::path::{ Path, PathBuf };
+use std::mem;
 use vulkano;
 use vulkano::{
     device::{
@@ -23,28 +33,40 @@ use vulkano::{
         debug::DebugCallback,
     },
     swapchain::{
+        AcquireError,
         PresentMode,
         Surface,
         SurfaceTransform,
         Swapchain,
+        SwapchainCreationError,
     },
-    pipeline::{
-        GraphicsPipeline,
-        vertex::SingleBufferDefinition,
-        viewport::Viewport,
-    },
+    pipeline::viewport::Viewport,
 };
 
 use vulkano::framebuffer::*;
 use vulkano::command_buffer::{ AutoCommandBuffer, DynamicState, AutoCommandBufferBuilder};
 use vulkano::sync::now;
 use vulkano::sync::GpuFuture;
+use vulkano::sync::FlushError;
+use vulkano::sync::FenceSignalFuture;
 use vulkano_win::{ VkSurfaceBuild, required_extensions };
-use vulkano::format::Format;
+use vulkano::format::{ ClearValue, Format };
 use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::{ ImageUsage, ImageViewAccess };
+use vulkano::buffer::{ CpuAccessibleBuffer, BufferUsage };
 
 use winit;
 use winit::EventsLoop;
+use image;
+use input::InputState;
+use render::config::Config;
+use render::reload::{ Change, HotReloader };
+use render::mesh::IndexBuffer;
+use render::texture::{ TextureCache, TextureHandle };
+use render::material::{ Material, MaterialParams, Shading };
+use render::pipeline::PipelineCache;
+use render::shader::CustomShader;
+use error::{ Error, Result };
 
 
 use cgmath::{
@@ -59,19 +81,43 @@ const VALIDATION_LAYERS: &[&str] =  &[
 ];
 
 
-#[cfg(all(debug_assertions))]
-const ENABLE_VALIDATION_LAYERS: bool = true;
-#[cfg(not(debug_assertions))]
-const ENABLE_VALIDATION_LAYERS: bool = false;
-
-
 #[derive(Debug, Clone)]
 pub struct Vertex {
     pub pos: [f32; 3],
     pub uv: [f32; 2],
-    // color: [f32; 3],
+    /// Used by `Shading::VertexColor`.
+    pub color: [f32; 3],
+}
+impl_vertex!(Vertex, pos, uv, color);
+
+/// Per-instance attributes of `Context::draw_instanced`: the columns of the
+/// model matrix and a color multiplied with the texture.
+#[derive(Debug, Clone, Copy)]
+pub struct InstanceData {
+    pub model_0: [f32; 4],
+    pub model_1: [f32; 4],
+    pub model_2: [f32; 4],
+    pub model_3: [f32; 4],
+    pub tint: [f32; 4],
+}
+impl_vertex!(InstanceData, model_0, model_1, model_2, model_3, tint);
+
+impl InstanceData {
+    pub fn new(model: Matrix4<f32>, tint: [f32; 4]) -> Self {
+        let model: [[f32; 4]; 4] = model.into();
+        Self {
+            model_0: model[0],
+            model_1: model[1],
+            model_2: model[2],
+            model_3: model[3],
+            tint,
+        }
+    }
+
+    pub fn from_transform(transform: &transform::Transform, tint: [f32; 4]) -> Self {
+        Self::new(transform.model_matrix(), tint)
+    }
 }
-impl_vertex!(Vertex, pos, uv);
 
 #[allow(unused)]
 pub mod vs {
@@ -91,66 +137,222 @@ pub mod fs {
 
     struct Dummy;
 }
+#[allow(unused)]
+pub mod instanced_vs {
+    #[derive(VulkanoShader)]
+    #[ty = "vertex"]
+    #[path = "src/shaders/instanced_vertex_shader.glsl"]
+    #[allow(dead_code)]
 
+    struct Dummy;
+}
+#[allow(unused)]
+pub mod instanced_fs {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/shaders/instanced_fragment_shader.glsl"]
+    #[allow(dead_code)]
 
-type ConcreteGraphicsPipeline = GraphicsPipeline<SingleBufferDefinition<Vertex>, std::boxed::Box<vulkano::descriptor::PipelineLayoutAbstract + std::marker::Send + std::marker::Sync>, std::sync::Arc<vulkano::framebuffer::RenderPassAbstract + std::marker::Send + std::marker::Sync>>;
+    struct Dummy;
+}
+#[allow(unused)]
+pub mod flat_fs {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/shaders/flat_fragment_shader.glsl"]
+    #[allow(dead_code)]
+
+    struct Dummy;
+}
+#[allow(unused)]
+pub mod color_fs {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "src/shaders/color_fragment_shader.glsl"]
+    #[allow(dead_code)]
+
+    struct Dummy;
+}
+
+const HEADLESS_COLOR_FORMAT: Format = Format::R8G8B8A8Srgb;
 
 
 pub struct Context {
-    pub surface: Arc<Surface<winit::Window>>,
-    pub swapchain: Arc<Swapchain<winit::Window>>,
+    pub surface: Option<Arc<Surface<winit::Window>>>,
+    pub swapchain: Option<Arc<Swapchain<winit::Window>>>,
     pub queue: Arc<Queue>,
     pub device: Arc<Device>,
     pub images: Vec<Arc<SwapchainImage<winit::Window>>>,
+    pub offscreen_image: Option<Arc<AttachmentImage>>,
     pub render_pass: Arc<RenderPassAbstract + Send + Sync>,
-    pub debug_callback: Arc<DebugCallback>,
+    pub debug_callback: Option<Arc<DebugCallback>>,
     pub dynamic_state: DynamicState,
     pub dpi_factor: f64,
     pub ubo: vulkano::buffer::CpuBufferPool<vs::ty::Data>,
-    pub graphics_pipeline: Arc<ConcreteGraphicsPipeline>,
-    pub swapchain_framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    pub instanced_ubo: vulkano::buffer::CpuBufferPool<instanced_vs::ty::Data>,
+    pub instance_pool: vulkano::buffer::CpuBufferPool<InstanceData>,
+    pub material_pool: vulkano::buffer::CpuBufferPool<MaterialParams>,
+    pub pipelines: PipelineCache,
+    pub framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    pub dimensions: [u32; 2],
+    /// Format of the swapchain or offscreen images frames are rendered to.
+    pub color_format: Format,
+    pub clear_color: [f32; 4],
+    pub config: Config,
+    pub textures: TextureCache,
+    /// White texture bound for materials without one.
+    pub default_texture: TextureHandle,
     pub meshs: Vec<mesh::Mesh>,
+    /// Meshes drawn once per instance, see `draw_instanced`.
+    pub instanced_meshs: Vec<(mesh::Mesh, Vec<InstanceData>)>,
     pub world: Matrix4<f32>,
     pub view:  Matrix4<f32>,
     pub projection: Matrix4<f32>,
+    pub input: InputState,
+    quit_requested: bool,
+    pending_screenshots: Vec<PathBuf>,
+    /// Set when the window changed and the swapchain no longer matches it.
+    recreate_swapchain: bool,
+    /// End of the last submitted frame, joined by the next one.
+    previous_frame_end: Option<Box<GpuFuture>>,
+    /// Fence of the last frame submitted in each of the `frames_in_flight` slots.
+    frame_fences: Vec<Option<Arc<FenceSignalFuture<Box<GpuFuture>>>>>,
+    frame_index: usize,
+    reloader: Option<HotReloader>,
 }
 
 impl Context{
     
-    pub fn new(width: u32, height: u32, title: &str) -> (Self, EventsLoop) {
-        let instance = {
+    pub fn new(mut config: Config) -> Result<(Self, EventsLoop)> {
+        let instance = Self::create_instance(&config, true)?;
+        let debug_callback = Self::create_debug_callback(&instance)?;
 
-            match InstanceExtensions::supported_by_core() {
-                Ok(i) => println!("Supportted extensions: {:?}", i),
-                Err(err) => panic!("Failed to retreive supported extensions: {:?}", err),
-            };
-            let mut extensions = required_extensions();
-            extensions.ext_debug_report = true;
+        let events_loop = winit::EventsLoop::new();
+        let monitor = if config.fullscreen {
+            Some(events_loop.get_primary_monitor())
+        } else {
+            None
+        };
+        let surface = winit::WindowBuilder::new()
+            .with_dimensions((config.width, config.height).into())
+            .with_title(config.title.clone())
+            .with_resizable(config.resizable)
+            .with_fullscreen(monitor)
+            .build_vk_surface(&events_loop, instance.clone())?;
 
-            if ENABLE_VALIDATION_LAYERS && !Self::check_validation_layer_support() {
-                println!("Validation layers requested, but not available!")
-            } else {
-                for layer in vulkano::instance::layers_list().unwrap() {
-                    println!("Available layer: {}", layer.name());
-                }
-            }
+        let physical = device::select(&instance, Some(&surface), config.preferred_gpu.as_ref().map(|s| s.as_str()))?;
+        config.samples = Self::pick_sample_count(physical, config.samples);
 
-            let app_info = ApplicationInfo {
-                application_name: Some(title.into()),
-                application_version: Some(Version { major: 1, minor: 0, patch: 0 }),
-                engine_name: Some("sw3d".into()),
-                engine_version: Some(Version {major: 1, minor: 0, patch: 0}),
-            };
+        let (device, queue) = Self::create_device(physical, Some(&surface))?;
 
-            if ENABLE_VALIDATION_LAYERS && Self::check_validation_layer_support() {
-                Instance::new(Some(&app_info), &extensions, VALIDATION_LAYERS.iter().map(|s| *s))
-                    .expect("failed to create Vulkan instance")
+        let (swapchain, images) = {
+
+            let caps = surface.capabilities(physical)?;
+            
+            let surface_dimensions = caps.current_extent.unwrap_or([config.width, config.height]);
+            let alpha = caps.supported_composite_alpha.iter().next()
+                .expect("Surfaces always support at least one composite alpha mode");
+            let format = caps.supported_formats[0].0;
+            let present_mode = if caps.present_modes.supports(config.present_mode) {
+                config.present_mode
             } else {
-                Instance::new(Some(&app_info), &extensions, None)
-                    .expect("failed to create Vulkan instance")
+                PresentMode::Fifo
+            };
+
+            Swapchain::new(device.clone(), surface.clone(), caps.min_image_count, format,
+                             surface_dimensions, 1, caps.supported_usage_flags, &queue,
+                             SurfaceTransform::Identity, alpha, present_mode, true,
+                             None)?
+        };
+
+        let targets = images.iter()
+            .map(|image| image.clone() as Arc<ImageViewAccess + Send + Sync>)
+            .collect::<Vec<_>>();
+
+        let mut ctx = Self::from_parts(device, queue, swapchain.format(), images[0].dimensions(), targets, debug_callback, config)?;
+        ctx.dpi_factor = surface.window().get_hidpi_factor();
+        ctx.surface = Some(surface);
+        ctx.swapchain = Some(swapchain);
+        ctx.images = images;
+
+        Ok((ctx, events_loop))
+    }
+
+    /// Creates a context without any window, rendering into an offscreen
+    /// color image that can be read back with `read_image`.
+    pub fn new_headless(mut config: Config) -> Result<Self> {
+        let instance = Self::create_instance(&config, false)?;
+        let debug_callback = Self::create_debug_callback(&instance)?;
+        let physical = device::select(&instance, None, config.preferred_gpu.as_ref().map(|s| s.as_str()))?;
+        config.samples = Self::pick_sample_count(physical, config.samples);
+
+        let (device, queue) = Self::create_device(physical, None)?;
+
+        let dimensions = [config.width, config.height];
+        let usage = ImageUsage {
+            color_attachment: true,
+            transfer_source: true,
+            .. ImageUsage::none()
+        };
+        let offscreen_image = AttachmentImage::with_usage(device.clone(), dimensions, HEADLESS_COLOR_FORMAT, usage)?;
+
+        let targets = vec![offscreen_image.clone() as Arc<ImageViewAccess + Send + Sync>];
+
+        let mut ctx = Self::from_parts(device, queue, HEADLESS_COLOR_FORMAT, dimensions, targets, debug_callback, config)?;
+        ctx.offscreen_image = Some(offscreen_image);
+
+        Ok(ctx)
+    }
+
+    fn create_instance(config: &Config, windowed: bool) -> Result<Arc<Instance>> {
+        let supported = match InstanceExtensions::supported_by_core() {
+            Ok(i) => {
+                debug!("Supported extensions: {:?}", i);
+                i
+            },
+            Err(err) => {
+                warn!("Failed to retrieve supported extensions: {:?}", err);
+                InstanceExtensions::none()
+            },
+        };
+
+        let mut extensions = if windowed {
+            required_extensions()
+        } else {
+            InstanceExtensions::none()
+        };
+        extensions.ext_debug_report = supported.ext_debug_report;
+
+        let validation = config.validation && Self::check_validation_layer_support()?;
+        if config.validation && !validation {
+            warn!("Validation layers requested, but not available!")
+        } else {
+            for layer in vulkano::instance::layers_list()? {
+                debug!("Available layer: {}", layer.name());
             }
+        }
+
+        let app_info = ApplicationInfo {
+            application_name: Some(config.title.clone().into()),
+            application_version: Some(Version { major: 1, minor: 0, patch: 0 }),
+            engine_name: Some("sw3d".into()),
+            engine_version: Some(Version {major: 1, minor: 0, patch: 0}),
         };
 
+        let instance = if validation {
+            Instance::new(Some(&app_info), &extensions, VALIDATION_LAYERS.iter().map(|s| *s))?
+        } else {
+            Instance::new(Some(&app_info), &extensions, None)?
+        };
+
+        Ok(instance)
+    }
+
+    fn create_debug_callback(instance: &Arc<Instance>) -> Result<Option<Arc<DebugCallback>>> {
+        if !instance.loaded_extensions().ext_debug_report {
+            return Ok(None);
+        }
+
         let debug_types = vulkano::instance::debug::MessageTypes {
             error: true,
             warning: true,
@@ -159,280 +361,801 @@ impl Context{
             debug: true,
         };
         
-        let debug_callback = Arc::new(DebugCallback::new(&instance.clone(), debug_types, |msg| {
-            println!("Debug callback: {:?}", msg.description);
-        }).expect("Failed to creaye debug callback"));
-
-
-        for physical_device in PhysicalDevice::enumerate(&instance.clone()) {
-            println!("Available device: {}", physical_device.name());
-        }
-
-        let cloned_instance = instance.clone();
-        let physical = match PhysicalDevice::enumerate(&cloned_instance).next() {
-            Some(i) => i,
-            None => panic!("No device available")
-        } ;
+        let debug_callback = DebugCallback::new(instance, debug_types, |msg| {
+            if msg.ty.error {
+                error!("[{}] {}", msg.layer_prefix, msg.description);
+            } else if msg.ty.warning || msg.ty.performance_warning {
+                warn!("[{}] {}", msg.layer_prefix, msg.description);
+            } else if msg.ty.information {
+                info!("[{}] {}", msg.layer_prefix, msg.description);
+            } else {
+                debug!("[{}] {}", msg.layer_prefix, msg.description);
+            }
+        })?;
 
-        let events_loop = winit::EventsLoop::new();
-        let surface = winit::WindowBuilder::new()
-            .with_dimensions((width, height).into())
-            .with_title(title)
-            .build_vk_surface(&events_loop, instance.clone()).unwrap();
+        Ok(Some(Arc::new(debug_callback)))
+    }
 
+    /// Creates the logical device with a single graphics queue. When a
+    /// surface is given, the queue must also be able to present to it.
+    fn create_device(physical: PhysicalDevice, surface: Option<&Arc<Surface<winit::Window>>>) -> Result<(Arc<Device>, Arc<Queue>)> {
         let queue_familie = physical.queue_families().find(|&q| {
-            q.supports_graphics() && surface.is_supported(q).unwrap_or(false)
-        }).expect("Failed to find a graphical queue family");
+            q.supports_graphics() && surface.map_or(true, |surface| surface.is_supported(q).unwrap_or(false))
+        }).ok_or(Error::NoQueueFamily)?;
 
         let device_ext = vulkano::device::DeviceExtensions {
-            khr_swapchain: true,
+            khr_swapchain: surface.is_some(),
             .. vulkano::device::DeviceExtensions::none()
         };
 
-        let (device, mut queues) = match Device::new(physical, physical.supported_features(), &device_ext,
-            [(queue_familie, 0.5)].iter().cloned()) {
-                Ok(i) => i,
-                Err(err) => panic!("Failed to create device: {:?}", err),
-        };
+        let (device, mut queues) = Device::new(physical, physical.supported_features(), &device_ext,
+            [(queue_familie, 0.5)].iter().cloned())?;
+
+        let queue = queues.next().expect("Device::new returns one queue per requested family");
 
-        let queue = queues.next().expect("Failed to get our queue");
+        Ok((device, queue))
+    }
 
-        let ((swapchain, images), surface_dimensions) = {
+    /// Builds everything that doesn't depend on where the frames end up:
+    /// render pass, pipelines, uniform pools and one framebuffer per target.
+    fn from_parts(device: Arc<Device>, queue: Arc<Queue>, color_format: Format, dimensions: [u32; 2],
+                  targets: Vec<Arc<ImageViewAccess + Send + Sync>>, debug_callback: Option<Arc<DebugCallback>>,
+                  config: Config) -> Result<Self> {
 
-            let caps = surface.capabilities(physical)
-                .expect("Failed to get surface capabilities");
-            
-            let surface_dimensions = caps.current_extent.unwrap_or([width, height]);
-            let alpha = caps.supported_composite_alpha.iter().next().unwrap();
-            let format = caps.supported_formats[0].0;
+        let dynamic_state = Self::create_dynamic_state(dimensions);
 
-            (Swapchain::new(device.clone(), surface.clone(), caps.min_image_count, format,
-                             surface_dimensions, 1, caps.supported_usage_flags, &queue,
-                             SurfaceTransform::Identity, alpha, PresentMode::Fifo, true,
-                             None).expect("failed to create swapchain"), surface_dimensions)
+        let render_pass = Self::create_render_pass(device.clone(), color_format, &config)?;
+
+        let dpi_factor = 1.0;
+
+        let ubo = vulkano::buffer::cpu_pool::CpuBufferPool::<vs::ty::Data>
+            ::new(device.clone(), vulkano::buffer::BufferUsage::all());
+
+        let instanced_ubo = vulkano::buffer::cpu_pool::CpuBufferPool::<instanced_vs::ty::Data>
+            ::new(device.clone(), vulkano::buffer::BufferUsage::all());
+        let instance_pool = vulkano::buffer::cpu_pool::CpuBufferPool::<InstanceData>
+            ::new(device.clone(), vulkano::buffer::BufferUsage::vertex_buffer());
+        let material_pool = vulkano::buffer::cpu_pool::CpuBufferPool::<MaterialParams>
+            ::new(device.clone(), vulkano::buffer::BufferUsage::uniform_buffer());
+
+        let pipelines = PipelineCache::new(device.clone(), render_pass.clone())?;
+
+        // Hot reloading is a development aid, a missing shader directory
+        // (e.g. running from another working directory) only disables it.
+        let reloader = if config.hot_reload {
+            match HotReloader::new(&config.shader_dir) {
+                Ok(reloader) => Some(reloader),
+                Err(err) => {
+                    warn!("Hot reloading disabled, cannot watch {}: {}", config.shader_dir.display(), err);
+                    None
+                },
+            }
+        } else {
+            None
         };
 
-        let dynamic_state = DynamicState {
+        let framebuffers = Self::create_framebuffers(device.clone(), render_pass.clone(), &targets, dimensions, color_format, &config)?;
+
+        let textures = TextureCache::new(device.clone(), queue.clone())?;
+        let default_texture = textures.solid([255, 255, 255, 255])?;
+        let meshs = Vec::new();
+
+        let world = Matrix4::identity();
+        let view = Matrix4::from_translation(Vector3::new(0.0, 0.0, -1.0));
+        let projection = Self::create_projection(dimensions);
+        let frames_in_flight = config.frames_in_flight.max(1);
+
+        Ok(Self {
+            surface: None,
+            swapchain: None,
+            queue,
+            device,
+            images: Vec::new(),
+            offscreen_image: None,
+            render_pass,
+            debug_callback,
+            dynamic_state,
+            dpi_factor,
+            ubo,
+            framebuffers,
+            instanced_ubo,
+            instance_pool,
+            material_pool,
+            pipelines,
+            dimensions,
+            color_format,
+            clear_color: config.clear_color,
+            config,
+            textures,
+            default_texture,
+            meshs,
+            instanced_meshs: Vec::new(),
+
+            world,
+            view,
+            projection,
+            input: InputState::new(),
+            quit_requested: false,
+            pending_screenshots: Vec::new(),
+            recreate_swapchain: false,
+            previous_frame_end: None,
+            frame_fences: vec![None; frames_in_flight],
+            frame_index: 0,
+            reloader,
+
+        })
+
+    }
+
+    fn create_dynamic_state(dimensions: [u32; 2]) -> DynamicState {
+        DynamicState {
             line_width: None,
             viewports: Some(vec![Viewport {
                 origin: [0.0, 0.0],
-                dimensions: [surface_dimensions[0] as f32, surface_dimensions[1] as f32],
+                dimensions: [dimensions[0] as f32, dimensions[1] as f32],
                 depth_range: 0.0 .. 1.0,
             }]),
             scissors: None,
-        };
+        }
+    }
+
+    fn create_projection(dimensions: [u32; 2]) -> Matrix4<f32> {
+        let aspect = dimensions[0] as f32 / dimensions[1].max(1) as f32;
+        cgmath::perspective(cgmath::Deg(45.0), aspect, 0.1, 100.0)
+    }
+
+    /// With more than one sample, drawing goes to a multisampled color
+    /// attachment that is resolved into the target at the end of the pass.
+    fn create_render_pass(device: Arc<Device>, color_format: Format, config: &Config) -> Result<Arc<RenderPassAbstract + Send + Sync>> {
+        if config.samples <= 1 {
+            return Ok(Arc::new(single_pass_renderpass!(device,
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: Store,
+                        format: color_format,
+                        samples: 1,
+                    },
+                    depth: {
+                        load: Clear,
+                        store: DontCare,
+                        format: config.depth_format,
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {depth}
+                }
+            )?));
+        }
 
-        let render_pass: Arc<RenderPassAbstract + Send + Sync> = Arc::new(single_pass_renderpass!(device.clone(),
+        Ok(Arc::new(single_pass_renderpass!(device,
             attachments: {
-                color: {
+                multisampled_color: {
                     load: Clear,
+                    store: DontCare,
+                    format: color_format,
+                    samples: config.samples,
+                },
+                color: {
+                    load: DontCare,
                     store: Store,
-                    format: swapchain.format(),
+                    format: color_format,
                     samples: 1,
                 },
                 depth: {
                     load: Clear,
                     store: DontCare,
-                    format: Format::D16Unorm,
-                    samples: 1,
+                    format: config.depth_format,
+                    samples: config.samples,
                 }
             },
             pass: {
-                color: [color],
-                depth_stencil: {depth}
+                color: [multisampled_color],
+                depth_stencil: {depth},
+                resolve: [color],
             }
-        ).unwrap());
+        )?))
+    }
 
-        let dpi_factor = 1.0;
+    /// Highest sample count up to `requested` that the device supports for
+    /// both color and depth attachments.
+    fn pick_sample_count(physical: PhysicalDevice, requested: u32) -> u32 {
+        let limits = physical.limits();
+        let supported = limits.framebuffer_color_sample_counts() & limits.framebuffer_depth_sample_counts();
 
-        let ubo = vulkano::buffer::cpu_pool::CpuBufferPool::<vs::ty::Data>
-            ::new(device.clone(), vulkano::buffer::BufferUsage::all());
+        let mut samples = requested.max(1).next_power_of_two();
+        if samples > requested.max(1) {
+            samples /= 2;
+        }
+        while samples > 1 && supported & samples == 0 {
+            samples /= 2;
+        }
 
-        
-        let vs = vs::Shader::load(device.clone())
-            .expect("Failed to create shader module");
-        
-        let fs = fs::Shader::load(device.clone())
-            .expect("Failed to create fragment module");
-
-
-        let graphics_pipeline = Arc::new(GraphicsPipeline::start()
-            //.vertex_input(vulkano::pipeline::vertex::TwoBuffersDefinition::new())
-            .vertex_input_single_buffer::<Vertex>()
-            .vertex_shader(vs.main_entry_point(), ())
-            .triangle_list()
-            .viewports_dynamic_scissors_irrelevant(1)
-            .fragment_shader(fs.main_entry_point(), ())
-            .depth_stencil_simple_depth()
-            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
-            .build(device.clone())
-            .expect("Failed to create graphics pipeline")
-            
-        );
+        if samples != requested.max(1) {
+            warn!("{}x MSAA is not supported, using {}x", requested, samples);
+        }
+        samples
+    }
+
+    /// One framebuffer per target, all sharing a single depth buffer and,
+    /// when multisampling, a single multisampled color attachment.
+    fn create_framebuffers(device: Arc<Device>, render_pass: Arc<RenderPassAbstract + Send + Sync>,
+                           targets: &[Arc<ImageViewAccess + Send + Sync>], dimensions: [u32; 2],
+                           color_format: Format, config: &Config) -> Result<Vec<Arc<FramebufferAbstract + Send + Sync>>> {
 
-        let dimensions = images[0].dimensions();
-        let depth_buffer = AttachmentImage::transient(device.clone(), dimensions, Format::D16Unorm).unwrap();
+        let mut framebuffers = Vec::with_capacity(targets.len());
 
-        let swapchain_framebuffers = images.iter()
-            .map(|image| {
+        if config.samples <= 1 {
+            let depth_buffer = AttachmentImage::transient(device, dimensions, config.depth_format)?;
+
+            for image in targets.iter() {
                 let fba: Arc<FramebufferAbstract + Send + Sync> = Arc::new(Framebuffer::start(render_pass.clone())
-                    .add(image.clone()).unwrap()
-                    .add(depth_buffer.clone()).unwrap()
-                    .build().expect("Failed to create framebuffers"));
-                    
-                    fba
+                    .add(image.clone())?
+                    .add(depth_buffer.clone())?
+                    .build()?);
+
+                framebuffers.push(fba);
             }
-        ).collect::<Vec<_>>();
+        } else {
+            let multisampled_color = AttachmentImage::transient_multisampled(device.clone(), dimensions, config.samples, color_format)?;
+            let depth_buffer = AttachmentImage::transient_multisampled(device, dimensions, config.samples, config.depth_format)?;
 
-        let meshs = Vec::new();
+            for image in targets.iter() {
+                let fba: Arc<FramebufferAbstract + Send + Sync> = Arc::new(Framebuffer::start(render_pass.clone())
+                    .add(multisampled_color.clone())?
+                    .add(image.clone())?
+                    .add(depth_buffer.clone())?
+                    .build()?);
 
-        let world = Matrix4::identity();
-        let view = Matrix4::from_translation(Vector3::new(0.0, 0.0, -1.0));
-        let projection = cgmath::perspective(cgmath::Deg(45.0), 800.0/800.0, 0.1, 100.0);
+                framebuffers.push(fba);
+            }
+        }
 
-        (Self {
-            surface,
-            swapchain,
-            queue,
-            device,
-            images,
-            render_pass,
-            debug_callback: debug_callback.clone(),
-            dynamic_state,
-            dpi_factor,
-            ubo,
-            swapchain_framebuffers,
-            graphics_pipeline,
-            meshs,
+        Ok(framebuffers)
+    }
 
-            world,
-            view,
-            projection,
+    /// Recompiles the GLSL sources in `config.shader_dir` and rebuilds the
+    /// graphics pipelines. On error the current pipelines are kept.
+    pub fn reload_shaders(&mut self) -> Result<()> {
+        self.pipelines.reload(&self.config.shader_dir)
+    }
+
+    /// Applies whatever the hot reloader noticed since the last frame.
+    /// Failures are logged and leave the previous texture or pipeline in place.
+    fn reload_assets(&mut self) {
+        let changes = match self.reloader {
+            Some(ref mut reloader) => reloader.changes(),
+            None => return,
+        };
+
+        for change in changes {
+            match change {
+                Change::Texture(path) => match self.textures.reload(&path) {
+                    Ok(true) => info!("Reloaded texture {}", path.display()),
+                    Ok(false) => {},
+                    Err(err) => error!("Failed to reload texture {}: {}", path.display(), err),
+                },
+                Change::Shaders => match self.reload_shaders() {
+                    Ok(()) => info!("Reloaded shaders"),
+                    Err(err) => error!("{}", err),
+                },
+            }
+        }
+    }
+
+    /// Loads user shaders from GLSL or SPIR-V (`.spv`) files, returning the
+    /// shading materials use to draw with them. See `CustomShader` for what
+    /// the shaders must look like.
+    pub fn load_shading<P: AsRef<Path>>(&mut self, vertex: P, fragment: P) -> Result<Shading> {
+        let shader = CustomShader::load(self.device.clone(), vertex, fragment)?;
+        self.pipelines.add_custom(shader)
+    }
+
+    /// Name of the GPU the context renders with.
+    pub fn device_name(&self) -> String {
+        self.device.physical_device().name()
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.swapchain.is_none()
+    }
+
+    /// Forces the swapchain to be recreated before the next frame. Called
+    /// by `Application` when the window is resized.
+    pub fn invalidate_swapchain(&mut self) {
+        self.recreate_swapchain = true;
+    }
+
+    /// Rebuilds the swapchain, depth buffer and framebuffers at the current
+    /// window size, and updates the viewport and projection to match.
+    /// Returns `false` when the window has no usable size, e.g. while minimized.
+    fn rebuild_swapchain(&mut self) -> Result<bool> {
+        let (swapchain, surface) = match (self.swapchain.clone(), self.surface.clone()) {
+            (Some(swapchain), Some(surface)) => (swapchain, surface),
+            _ => return Ok(false),
+        };
+
+        let window = surface.window();
+        let dimensions: (u32, u32) = match window.get_inner_size() {
+            Some(size) => size.to_physical(window.get_hidpi_factor()).into(),
+            None => return Ok(false),
+        };
+        if dimensions.0 == 0 || dimensions.1 == 0 {
+            return Ok(false);
+        }
+
+        let (swapchain, images) = match swapchain.recreate_with_dimension([dimensions.0, dimensions.1]) {
+            Ok(r) => r,
+            Err(SwapchainCreationError::UnsupportedDimensions) => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+
+        let dimensions = swapchain.dimensions();
+        let targets = images.iter()
+            .map(|image| image.clone() as Arc<ImageViewAccess + Send + Sync>)
+            .collect::<Vec<_>>();
+
+        self.framebuffers = Self::create_framebuffers(self.device.clone(), self.render_pass.clone(),
+            &targets, dimensions, swapchain.format(), &self.config)?;
+        self.dynamic_state = Self::create_dynamic_state(dimensions);
+        self.projection = Self::create_projection(dimensions);
+        self.dimensions = dimensions;
+        self.swapchain = Some(swapchain);
+        self.images = images;
+        self.recreate_swapchain = false;
+        debug!("Swapchain recreated at {}x{}", dimensions[0], dimensions[1]);
+
+        Ok(true)
+    }
+
+    /// Asks the application to stop once the current frame is done.
+    pub fn quit(&mut self) {
+        self.quit_requested = true;
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit_requested
+    }
+
+    pub fn update(&mut self) -> Result<()> {
+        self.reload_assets();
+
+        let result = if self.is_headless() {
+            self.render_offscreen()
+        } else {
+            self.present()
+        };
+        self.save_screenshots();
+        self.meshs.clear();
+        self.instanced_meshs.clear();
+
+        result
+    }
+
+    /// Saves the current frame to `path` once it has been rendered, at the
+    /// size of the window. The format is picked from the file extension.
+    /// Failures are logged, they don't stop the application.
+    pub fn screenshot<P: AsRef<Path>>(&mut self, path: P) {
+        self.pending_screenshots.push(path.as_ref().to_owned());
+    }
+
+    fn save_screenshots(&mut self) {
+        if self.pending_screenshots.is_empty() {
+            return;
+        }
+
+        let paths = mem::replace(&mut self.pending_screenshots, Vec::new());
+        let (width, height) = (self.dimensions[0], self.dimensions[1]);
+        let image = match self.render_to_image(width, height) {
+            Ok(image) => image,
+            Err(err) => {
+                error!("Failed to capture a screenshot: {}", err);
+                return;
+            },
+        };
 
+        for path in paths {
+            match image.save(&path) {
+                Ok(()) => info!("Saved screenshot to {}", path.display()),
+                Err(err) => error!("Failed to save screenshot to {}: {}", path.display(), err),
+            }
+        }
+    }
+
+    /// Renders the meshes queued for this frame into a new offscreen image
+    /// and reads it back. The projection is left as is, so a size with
+    /// another aspect ratio than the window stretches the picture.
+    pub fn render_to_image(&mut self, width: u32, height: u32) -> Result<image::RgbaImage> {
+        if !capture::is_supported(self.color_format) {
+            return Err(Error::UnsupportedFormat(self.color_format));
+        }
+
+        let dimensions = [width.max(1), height.max(1)];
+        let usage = ImageUsage {
+            color_attachment: true,
+            transfer_source: true,
+            .. ImageUsage::none()
+        };
+        let target = AttachmentImage::with_usage(self.device.clone(), dimensions, self.color_format, usage)?;
 
-        }, events_loop)
+        let framebuffer = Self::create_framebuffers(self.device.clone(), self.render_pass.clone(),
+            &[target.clone() as Arc<ImageViewAccess + Send + Sync>], dimensions, self.color_format, &self.config)?
+            .remove(0);
 
+        let dynamic_state = mem::replace(&mut self.dynamic_state, Self::create_dynamic_state(dimensions));
+        let command_buffer = self.record_frame(framebuffer);
+        self.dynamic_state = dynamic_state;
+
+        now(self.device.clone())
+            .then_execute(self.queue.clone(), command_buffer?)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+
+        self.read_back(target, dimensions)
     }
 
-    pub fn update(&mut self) {
-        let mut previous_frame_end = Box::new(now(self.device.clone())) as Box<GpuFuture>;
+    /// An out-of-date swapchain is not an error: the frame is dropped and
+    /// the swapchain recreated on the next one. Vulkano reports suboptimal
+    /// swapchains as successes, those are caught by the resize events.
+    ///
+    /// Up to `config.frames_in_flight` frames are queued on the GPU; before
+    /// recording a frame we wait on the fence of the one that last used its
+    /// slot, which keeps the CPU at most that many frames ahead.
+    fn present(&mut self) -> Result<()> {
+        if let Some(ref mut previous_frame_end) = self.previous_frame_end {
             previous_frame_end.cleanup_finished();
-            
-            
-            let (image_num, acquire_future) = swapchain::acquire_next_image(self.swapchain.clone(), None).unwrap();
-            let command_buffer: AutoCommandBuffer = self.draw_meshs(image_num);
-            self.meshs.clear();
+        }
+
+        if let Some(ref fence) = self.frame_fences[self.frame_index] {
+            fence.wait(None)?;
+        }
+
+        if self.recreate_swapchain && !self.rebuild_swapchain()? {
+            return Ok(());
+        }
+        let swapchain = self.swapchain.clone().expect("Windowed contexts have a swapchain");
+
+        let (image_num, acquire_future) = match swapchain::acquire_next_image(swapchain.clone(), None) {
+            Ok(r) => r,
+            Err(AcquireError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                return Ok(());
+            },
+            Err(err) => return Err(err.into()),
+        };
+        let command_buffer: AutoCommandBuffer = self.draw_meshs(image_num)?;
+
+        let previous_frame_end = self.previous_frame_end.take()
+            .unwrap_or_else(|| Box::new(now(self.device.clone())) as Box<GpuFuture>);
+
+        let future = Box::new(previous_frame_end.join(acquire_future)
+            .then_execute(self.queue.clone(), command_buffer)?
+            .then_swapchain_present(self.queue.clone(), swapchain, image_num)) as Box<GpuFuture>;
+
+        let result = match future.then_signal_fence_and_flush() {
+            Ok(fence) => {
+                let fence = Arc::new(fence);
+                self.frame_fences[self.frame_index] = Some(fence.clone());
+                self.previous_frame_end = Some(Box::new(fence) as Box<GpuFuture>);
+                Ok(())
+            },
+            Err(FlushError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                Ok(())
+            },
+            Err(err) => Err(err.into()),
+        };
+        self.frame_index = (self.frame_index + 1) % self.frame_fences.len();
+
+        result
+    }
+
+    /// Headless frames are waited on right away, so the offscreen image
+    /// always holds the last finished frame.
+    fn render_offscreen(&mut self) -> Result<()> {
+        let command_buffer: AutoCommandBuffer = self.draw_meshs(0)?;
+
+        now(self.device.clone())
+            .then_execute(self.queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+
+        Ok(())
+    }
+
+    /// Copies the offscreen image of a headless context back to CPU memory.
+    pub fn read_image(&self) -> Result<image::RgbaImage> {
+        let target = match self.offscreen_image {
+            Some(ref image) => image.clone(),
+            None => return Err(Error::NoOffscreenImage),
+        };
+
+        self.read_back(target, self.dimensions)
+    }
+
+    /// Copies a color image to CPU memory, converting it to RGBA.
+    fn read_back(&self, target: Arc<AttachmentImage>, dimensions: [u32; 2]) -> Result<image::RgbaImage> {
+        let (width, height) = (dimensions[0], dimensions[1]);
+
+        let buffer = CpuAccessibleBuffer::from_iter(self.device.clone(), BufferUsage::all(),
+            (0 .. width * height * 4).map(|_| 0u8))?;
 
-            let future = previous_frame_end.join(acquire_future)
-                .then_execute(self.queue.clone(), command_buffer).unwrap()
-                .then_swapchain_present(self.queue.clone(), self.swapchain.clone(), image_num)
-                .then_signal_fence_and_flush().unwrap();
+        let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family())?
+            .copy_image_to_buffer(target, buffer.clone())?
+            .build()?;
 
-            //previous_frame_end = Box::new(future) as Box<_>;
-                      
+        now(self.device.clone())
+            .then_execute(self.queue.clone(), command_buffer)?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
 
+        let content = buffer.read()?;
+        capture::to_rgba(self.color_format, width, height, content.to_vec())
     }
     
-    fn check_validation_layer_support() -> bool {
-        let layers: Vec<_> = layers_list().unwrap().map(|l| l.name().to_owned()).collect();
-        VALIDATION_LAYERS.iter()
-            .all(|layer_name| layers.contains(&layer_name.to_string()))
+    fn check_validation_layer_support() -> Result<bool> {
+        let layers: Vec<_> = layers_list()?.map(|l| l.name().to_owned()).collect();
+        Ok(VALIDATION_LAYERS.iter()
+            .all(|layer_name| layers.contains(&layer_name.to_string())))
     }
 
-    pub fn draw_meshs(&mut self, image_num: usize ) -> AutoCommandBuffer {         
-        let mut _command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family()).unwrap()
+    pub fn draw_meshs(&mut self, image_num: usize ) -> Result<AutoCommandBuffer> {         
+        let framebuffer = self.framebuffers[image_num].clone();
+        self.record_frame(framebuffer)
+    }
+
+    fn record_frame(&mut self, framebuffer: Arc<FramebufferAbstract + Send + Sync>) -> Result<AutoCommandBuffer> {
+        let _command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(self.device.clone(), self.queue.family())?
             .begin_render_pass(
-                self.swapchain_framebuffers[image_num].clone(), false,vec![[0.0, 0.0, 0.0, 1.0].into(), 1f32.into()])
-                    .unwrap()
-                    .draw_mesh(self);
+                framebuffer, false, self.clear_values())?
+                    .draw_mesh(self)?;
 
-        let command_buffer = _command_buffer.end_render_pass().unwrap()
-            .build().unwrap();
+        let command_buffer = _command_buffer.end_render_pass()?
+            .build()?;
                 
-        command_buffer
+        Ok(command_buffer)
     }
 
-    fn draw_mesh(&mut self, mut command_buffer: AutoCommandBufferBuilder) -> AutoCommandBufferBuilder {
-        for mesh in self.meshs.iter() {
+    /// One per attachment; the resolved color target is not cleared.
+    fn clear_values(&self) -> Vec<ClearValue> {
+        if self.config.samples <= 1 {
+            vec![self.clear_color.into(), 1f32.into()]
+        } else {
+            vec![self.clear_color.into(), ClearValue::None, 1f32.into()]
+        }
+    }
 
-            let uniform_data = mesh.update(self.view.into(), self.projection.into(), self.world.into());
-            let uniform_buffer_subbuffer = self.ubo.next(uniform_data).unwrap();
+    /// Opaque meshes are drawn first, then transparent ones, each in the
+    /// order they were queued.
+    fn draw_mesh(&mut self, mut command_buffer: AutoCommandBufferBuilder) -> Result<AutoCommandBufferBuilder> {
+        for &transparent in [false, true].iter() {
+            command_buffer = self.draw_single(command_buffer, transparent)?;
+            command_buffer = self.draw_instances(command_buffer, transparent)?;
+        }
+
+        Ok(command_buffer)
+    }
 
-            let set = Arc::new(vulkano::descriptor::descriptor_set::PersistentDescriptorSet::start(self.graphics_pipeline.clone(), 0)
-                .add_sampled_image(mesh.texture.clone(), mesh.sampler.clone()).unwrap()
-                .add_buffer(uniform_buffer_subbuffer).unwrap()
-                .build().unwrap()
+    fn draw_single(&mut self, mut command_buffer: AutoCommandBufferBuilder, transparent: bool) -> Result<AutoCommandBufferBuilder> {
+        for mesh in self.meshs.iter().filter(|mesh| mesh.material.is_transparent() == transparent) {
+            let material = &mesh.material;
+            let pipeline = self.pipelines.get(material.shading, material.render_state)?;
+            let texture = material.texture.as_ref().unwrap_or(&self.default_texture);
+
+            let uniform_data = mesh.update(self.view.into(), self.projection.into(), self.world.into());
+            let uniform_buffer_subbuffer = self.ubo.next(uniform_data)?;
+            let material_buffer = self.material_pool.next(material.params)?;
+
+            let set = Arc::new(vulkano::descriptor::descriptor_set::PersistentDescriptorSet::start(pipeline.clone(), 0)
+                .add_sampled_image(texture.image(), texture.sampler())?
+                .add_buffer(uniform_buffer_subbuffer)?
+                .add_buffer(material_buffer)?
+                .build()?
             );
       
-            command_buffer = command_buffer
-                .draw(
-                self.graphics_pipeline.clone(),
-                &self.dynamic_state,
-                mesh.vertex_buffer.clone(), 
-                set.clone(), ()).unwrap()
+            command_buffer = match mesh.index_buffer {
+                None => command_buffer.draw(
+                    pipeline,
+                    &self.dynamic_state,
+                    mesh.vertex_buffer.clone(), 
+                    set, material.push_constants)?,
+                Some(IndexBuffer::U16(ref indices)) => command_buffer.draw_indexed(
+                    pipeline,
+                    &self.dynamic_state,
+                    mesh.vertex_buffer.clone(),
+                    indices.clone(),
+                    set, material.push_constants)?,
+                Some(IndexBuffer::U32(ref indices)) => command_buffer.draw_indexed(
+                    pipeline,
+                    &self.dynamic_state,
+                    mesh.vertex_buffer.clone(),
+                    indices.clone(),
+                    set, material.push_constants)?,
+            };
+        }
+
+        Ok(command_buffer)
+    }
+
+    fn draw_instances(&mut self, mut command_buffer: AutoCommandBufferBuilder, transparent: bool) -> Result<AutoCommandBufferBuilder> {
+        for (mesh, instances) in self.instanced_meshs.iter() {
+            if instances.is_empty() || mesh.material.is_transparent() != transparent {
+                continue;
+            }
+
+            let material = &mesh.material;
+            let pipeline = self.pipelines.get_instanced(material.shading, material.render_state)?;
+            let texture = material.texture.as_ref().unwrap_or(&self.default_texture);
+
+            let uniform_buffer_subbuffer = self.instanced_ubo.next(instanced_vs::ty::Data {
+                projection: self.projection.into(),
+                view: self.view.into(),
+            })?;
+            let material_buffer = self.material_pool.next(material.params)?;
+            let instance_buffer = self.instance_pool.chunk(instances.iter().cloned())?;
+
+            let set = Arc::new(vulkano::descriptor::descriptor_set::PersistentDescriptorSet::start(pipeline.clone(), 0)
+                .add_sampled_image(texture.image(), texture.sampler())?
+                .add_buffer(uniform_buffer_subbuffer)?
+                .add_buffer(material_buffer)?
+                .build()?
+            );
+
+            let vertices = (mesh.vertex_buffer.clone(), instance_buffer);
+            command_buffer = match mesh.index_buffer {
+                None => command_buffer.draw(
+                    pipeline,
+                    &self.dynamic_state,
+                    vertices,
+                    set, ())?,
+                Some(IndexBuffer::U16(ref indices)) => command_buffer.draw_indexed(
+                    pipeline,
+                    &self.dynamic_state,
+                    vertices,
+                    indices.clone(),
+                    set, ())?,
+                Some(IndexBuffer::U32(ref indices)) => command_buffer.draw_indexed(
+                    pipeline,
+                    &self.dynamic_state,
+                    vertices,
+                    indices.clone(),
+                    set, ())?,
+            };
         }
 
-        command_buffer
+        Ok(command_buffer)
     }
 
     pub fn draw(&mut self, mesh: mesh::Mesh) {
+        self.watch_texture(&mesh.material);
         self.meshs.push(mesh);
     }
 
+    /// Draws `mesh` with another shading for this frame only, keeping the
+    /// rest of its material, e.g. `Shading::Wireframe` to inspect geometry.
+    pub fn draw_as(&mut self, mesh: mesh::Mesh, shading: Shading) {
+        let material = Arc::new(mesh.material.as_ref().clone().with_shading(shading));
+        self.draw(mesh.with_material(material));
+    }
+
+    /// Draws `mesh` once per instance with a single draw call. The mesh's
+    /// own transform is ignored, each instance carries its model matrix.
+    pub fn draw_instanced(&mut self, mesh: mesh::Mesh, instances: Vec<InstanceData>) {
+        self.watch_texture(&mesh.material);
+        self.instanced_meshs.push((mesh, instances));
+    }
+
+    fn watch_texture(&mut self, material: &Material) {
+        let texture = match material.texture {
+            Some(ref texture) => texture,
+            None => return,
+        };
+        if let Some(ref mut reloader) = self.reloader {
+            if let Err(err) = reloader.watch_texture(texture.path()) {
+                warn!("Cannot watch {}: {}", texture.path().display(), err);
+            }
+        }
+    }
+
 
 }
 
 
 pub trait DrawMeshTrait {
-    fn draw_mesh(self, data: &mut Context) -> AutoCommandBufferBuilder;
+    fn draw_mesh(self, data: &mut Context) -> Result<AutoCommandBufferBuilder>;
 }
 
 impl DrawMeshTrait for AutoCommandBufferBuilder {
-    fn draw_mesh(self, data: &mut Context) -> AutoCommandBufferBuilder {
+    fn draw_mesh(self, data: &mut Context) -> Result<AutoCommandBufferBuilder> {
         data.draw_mesh(self)
     }
 }
 
 pub const CUBE : [Vertex;36] = [
-        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 0.0]},
-        Vertex { pos: [0.5, -0.5, -0.5], uv: [1.0, 0.0]},
-        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0]},
-        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0]},
-        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 0.0, 1.0]},
-        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 0.0]},
-
-        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0]},
-        Vertex { pos: [0.5, -0.5,  0.5], uv: [1.0, 0.0]},
-        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 1.0]},
-        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 1.0]},
-        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 0.0, 1.0]},
-        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0]},
-
-        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 1.0, 0.0]},
-        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 1.0, 1.0]},
-        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0]},
-        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0]},
-        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0]},
-        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 1.0, 0.0]},
-
-        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0]},
-        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0]},
-        Vertex { pos: [0.5, -0.5, -0.5], uv: [0.0, 1.0]},
-        Vertex { pos: [0.5, -0.5, -0.5], uv: [0.0, 1.0]},
-        Vertex { pos: [0.5, -0.5,  0.5], uv: [0.0, 0.0]},
-        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0]},
-
-        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0]},
-        Vertex { pos: [0.5, -0.5, -0.5], uv: [1.0, 1.0]},
-        Vertex { pos: [0.5, -0.5,  0.5], uv: [1.0, 0.0]},
-        Vertex { pos: [0.5, -0.5,  0.5], uv: [1.0, 0.0]},
-        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0]},
-        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0]},
-
-        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 0.0, 1.0]},
-        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0]},
-        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0]},
-        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0]},
-        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 0.0, 0.0]},
-        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 0.0, 1.0]},
-        ];
\ No newline at end of file
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5, -0.5], uv: [1.0, 0.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 0.0] },
+        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 0.0] },
+        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 0.0] },
+
+        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 1.0] },
+        Vertex { pos: [0.5, -0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 0.0, 1.0] },
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 1.0] },
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 1.0] },
+        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 0.0, 1.0], color: [0.0, 1.0, 1.0] },
+        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 1.0] },
+
+        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 1.0, 0.0], color: [0.0, 1.0, 1.0] },
+        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 1.0, 1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 0.0, 0.0] },
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 0.0, 0.0] },
+        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 1.0] },
+        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 1.0, 0.0], color: [0.0, 1.0, 1.0] },
+
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 1.0, 1.0] },
+        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 0.0] },
+        Vertex { pos: [0.5, -0.5, -0.5], uv: [0.0, 1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5, -0.5], uv: [0.0, 1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5,  0.5], uv: [0.0, 0.0], color: [1.0, 0.0, 1.0] },
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 1.0, 1.0] },
+
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 0.0, 1.0] },
+        Vertex { pos: [0.5, -0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 0.0, 1.0] },
+        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 1.0] },
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 0.0, 0.0] },
+
+        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 0.0] },
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 1.0, 1.0] },
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 1.0, 1.0] },
+        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 1.0, 1.0] },
+        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 1.0, 0.0] },
+        ];
+
+/// `CUBE` without duplicated vertices, to draw with `CUBE_INDICES`. Both are
+/// colored by position, from black at one corner to white at the opposite one.
+pub const CUBE_VERTICES : [Vertex;24] = [
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5, -0.5], uv: [1.0, 0.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 0.0] },
+        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 1.0, 0.0] },
+
+        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 1.0] },
+        Vertex { pos: [0.5, -0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 0.0, 1.0] },
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 1.0] },
+        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 0.0, 1.0], color: [0.0, 1.0, 1.0] },
+
+        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 1.0, 0.0], color: [0.0, 1.0, 1.0] },
+        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 1.0, 1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 0.0, 0.0] },
+        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 1.0] },
+
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 1.0, 1.0] },
+        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 0.0] },
+        Vertex { pos: [0.5, -0.5, -0.5], uv: [0.0, 1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5,  0.5], uv: [0.0, 0.0], color: [1.0, 0.0, 1.0] },
+
+        Vertex { pos: [-0.5, -0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [0.5, -0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 0.0, 1.0] },
+        Vertex { pos: [-0.5, -0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 0.0, 1.0] },
+
+        Vertex { pos: [-0.5,  0.5, -0.5], uv: [ 0.0, 1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [0.5,  0.5, -0.5], uv: [1.0, 1.0], color: [1.0, 1.0, 0.0] },
+        Vertex { pos: [0.5,  0.5,  0.5], uv: [1.0, 0.0], color: [1.0, 1.0, 1.0] },
+        Vertex { pos: [-0.5,  0.5,  0.5], uv: [ 0.0, 0.0], color: [0.0, 1.0, 1.0] },
+        ];
+
+pub const CUBE_INDICES : [u16;36] = [
+        0, 1, 2, 2, 3, 0,
+        4, 5, 6, 6, 7, 4,
+        8, 9, 10, 10, 11, 8,
+        12, 13, 14, 14, 15, 12,
+        16, 17, 18, 18, 19, 16,
+        20, 21, 22, 22, 23, 20,
+        ];
@@ -0,0 +1,288 @@
+use std;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::path::Path;
+use std::sync::Arc;
+
+use vulkano;
+use vulkano::descriptor::descriptor::ShaderStages;
+use vulkano::device::Device;
+use vulkano::framebuffer::{ RenderPassAbstract, Subpass };
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::blend::{ AttachmentBlend, BlendFactor };
+use vulkano::pipeline::depth_stencil::{ Compare, DepthStencil };
+use vulkano::pipeline::shader::{ GraphicsShaderType, ShaderModule };
+use vulkano::pipeline::vertex::{ OneVertexOneInstanceDefinition, SingleBufferDefinition };
+
+use error::{ Error, Result };
+use render::{ Vertex, InstanceData, vs, fs, instanced_vs, instanced_fs, flat_fs, color_fs };
+use render::material::{ Blend, CullMode, RenderState, Shading };
+use render::reload;
+use render::shader::{ CustomShader, ShaderId };
+
+
+pub type ConcreteGraphicsPipeline = GraphicsPipeline<SingleBufferDefinition<Vertex>, std::boxed::Box<vulkano::descriptor::PipelineLayoutAbstract + std::marker::Send + std::marker::Sync>, std::sync::Arc<vulkano::framebuffer::RenderPassAbstract + std::marker::Send + std::marker::Sync>>;
+
+pub type InstancedGraphicsPipeline = GraphicsPipeline<OneVertexOneInstanceDefinition<Vertex, InstanceData>, std::boxed::Box<vulkano::descriptor::PipelineLayoutAbstract + std::marker::Send + std::marker::Sync>, std::sync::Arc<vulkano::framebuffer::RenderPassAbstract + std::marker::Send + std::marker::Sync>>;
+
+/// Entry point of a shader module, assuming it has the interface of the
+/// one `$shader` was generated from.
+macro_rules! entry_point {
+    ($module:expr, $shader:ident, $stage:ident, $ty:ident) => {
+        unsafe {
+            $module.graphics_entry_point(CStr::from_bytes_with_nul_unchecked(b"main\0"),
+                $shader::MainInput, $shader::MainOutput,
+                $shader::Layout(ShaderStages { $stage: true, .. ShaderStages::none() }),
+                GraphicsShaderType::$ty)
+        }
+    };
+}
+
+/// Applies a `RenderState` to a pipeline builder.
+macro_rules! with_render_state {
+    ($builder:expr, $state:expr) => {{
+        let state: RenderState = $state;
+        let builder = $builder;
+
+        let builder = match state.cull_mode {
+            CullMode::None => builder.cull_mode_disabled(),
+            CullMode::Front => builder.cull_mode_front(),
+            CullMode::Back => builder.cull_mode_back(),
+        };
+
+        let builder = builder.depth_stencil(DepthStencil {
+            depth_compare: if state.depth_test { Compare::Less } else { Compare::Always },
+            depth_write: state.depth_write,
+            .. DepthStencil::simple_depth_test()
+        });
+
+        match state.blend {
+            Blend::Opaque => builder.blend_pass_through(),
+            Blend::Alpha => builder.blend_alpha_blending(),
+            Blend::Additive => builder.blend_collective(AttachmentBlend {
+                color_destination: BlendFactor::One,
+                alpha_destination: BlendFactor::One,
+                .. AttachmentBlend::alpha_blending()
+            }),
+        }
+    }};
+}
+
+/// Builds a pipeline from a vertex input definition and shader entry points.
+macro_rules! build_pipeline {
+    ($device:expr, $render_pass:expr, $vertex_input:expr, $vs:expr, $fs:expr, $state:expr, $wireframe:expr) => {{
+        let builder = GraphicsPipeline::start()
+            .vertex_input($vertex_input)
+            .vertex_shader($vs, ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            // vulkano refuses to link stages whose interfaces differ in
+            // length, so every fragment shader declares all the vertex
+            // shader outputs, used or not.
+            .fragment_shader($fs, ());
+
+        let builder = if $wireframe {
+            builder.polygon_mode_line()
+        } else {
+            builder
+        };
+
+        let pipeline = with_render_state!(builder, $state)
+            .render_pass(Subpass::from($render_pass, 0).expect("Render pass has one subpass"))
+            .build($device)?;
+
+        Arc::new(pipeline)
+    }};
+}
+
+
+/// Shader modules the pipelines are built from. They start as the ones
+/// compiled into the binary and are swapped by hot reloading.
+struct ShaderSet {
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    instanced_vs: Arc<ShaderModule>,
+    instanced_fs: Arc<ShaderModule>,
+    flat_fs: Arc<ShaderModule>,
+    color_fs: Arc<ShaderModule>,
+}
+
+impl ShaderSet {
+    fn load(device: Arc<Device>) -> Result<Self> {
+        Ok(Self {
+            vs: vs::Shader::load(device.clone())?.module().clone(),
+            fs: fs::Shader::load(device.clone())?.module().clone(),
+            instanced_vs: instanced_vs::Shader::load(device.clone())?.module().clone(),
+            instanced_fs: instanced_fs::Shader::load(device.clone())?.module().clone(),
+            flat_fs: flat_fs::Shader::load(device.clone())?.module().clone(),
+            color_fs: color_fs::Shader::load(device)?.module().clone(),
+        })
+    }
+
+    /// Only the shader bodies may change: the inputs, outputs and uniforms
+    /// must stay the ones compiled into the binary, see `reload::load_shader`.
+    fn compile(device: Arc<Device>, dir: &Path) -> Result<Self> {
+        Ok(Self {
+            vs: reload::load_shader(device.clone(), dir, &reload::VERTEX_SHADER)?,
+            fs: reload::load_shader(device.clone(), dir, &reload::FRAGMENT_SHADER)?,
+            instanced_vs: reload::load_shader(device.clone(), dir, &reload::INSTANCED_VERTEX_SHADER)?,
+            instanced_fs: reload::load_shader(device.clone(), dir, &reload::INSTANCED_FRAGMENT_SHADER)?,
+            flat_fs: reload::load_shader(device.clone(), dir, &reload::FLAT_FRAGMENT_SHADER)?,
+            color_fs: reload::load_shader(device, dir, &reload::COLOR_FRAGMENT_SHADER)?,
+        })
+    }
+}
+
+/// Builds pipelines on first use and keeps them, one per shading and
+/// render state combination.
+pub struct PipelineCache {
+    device: Arc<Device>,
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    shaders: ShaderSet,
+    custom: Vec<CustomShader>,
+    pipelines: HashMap<(Shading, RenderState), Arc<ConcreteGraphicsPipeline>>,
+    instanced: HashMap<(Shading, RenderState), Arc<InstancedGraphicsPipeline>>,
+}
+
+impl PipelineCache {
+    pub fn new(device: Arc<Device>, render_pass: Arc<RenderPassAbstract + Send + Sync>) -> Result<Self> {
+        let shaders = ShaderSet::load(device.clone())?;
+
+        let mut cache = Self {
+            device,
+            render_pass,
+            shaders,
+            custom: Vec::new(),
+            pipelines: HashMap::new(),
+            instanced: HashMap::new(),
+        };
+        // Fail early rather than on the first draw.
+        cache.get(Shading::Textured, RenderState::default())?;
+        cache.get_instanced(Shading::Textured, RenderState::default())?;
+
+        Ok(cache)
+    }
+
+    pub fn get(&mut self, shading: Shading, state: RenderState) -> Result<Arc<ConcreteGraphicsPipeline>> {
+        if let Some(pipeline) = self.pipelines.get(&(shading, state)) {
+            return Ok(pipeline.clone());
+        }
+
+        let pipeline = build_pipeline(self.device.clone(), self.render_pass.clone(), &self.shaders, &self.custom, shading, state)?;
+        self.pipelines.insert((shading, state), pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Registers user shaders, returning the shading that draws with them.
+    /// A pipeline is built right away so mistakes show up here.
+    pub fn add_custom(&mut self, shader: CustomShader) -> Result<Shading> {
+        let shading = Shading::Custom(ShaderId::new(self.custom.len()));
+        self.custom.push(shader);
+
+        if let Err(err) = self.get(shading, RenderState::default()) {
+            self.custom.pop();
+            return Err(err);
+        }
+
+        Ok(shading)
+    }
+
+    pub fn custom(&self, id: ShaderId) -> Option<&CustomShader> {
+        self.custom.get(id.index())
+    }
+
+    pub fn get_instanced(&mut self, shading: Shading, state: RenderState) -> Result<Arc<InstancedGraphicsPipeline>> {
+        if let Some(pipeline) = self.instanced.get(&(shading, state)) {
+            return Ok(pipeline.clone());
+        }
+
+        let pipeline = build_instanced_pipeline(self.device.clone(), self.render_pass.clone(), &self.shaders, shading, state)?;
+        self.instanced.insert((shading, state), pipeline.clone());
+        Ok(pipeline)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.len() + self.instanced.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Recompiles the GLSL sources in `dir` and drops every cached pipeline.
+    /// On error the current shaders and pipelines are kept. Custom shaders
+    /// are not reloaded.
+    pub fn reload(&mut self, dir: &Path) -> Result<()> {
+        let shaders = ShaderSet::compile(self.device.clone(), dir)?;
+
+        // Building the default pipelines checks the new shaders link.
+        let state = RenderState::default();
+        let pipeline = build_pipeline(self.device.clone(), self.render_pass.clone(), &shaders, &self.custom, Shading::Textured, state)?;
+        let instanced = build_instanced_pipeline(self.device.clone(), self.render_pass.clone(), &shaders, Shading::Textured, state)?;
+
+        self.shaders = shaders;
+        self.pipelines.clear();
+        self.instanced.clear();
+        self.pipelines.insert((Shading::Textured, state), pipeline);
+        self.instanced.insert((Shading::Textured, state), instanced);
+
+        Ok(())
+    }
+}
+
+/// Whether `shading` draws lines, falling back to filled triangles when
+/// the device can't.
+fn wireframe(device: &Arc<Device>, shading: Shading) -> bool {
+    if shading != Shading::Wireframe {
+        return false;
+    }
+    if !device.enabled_features().fill_mode_non_solid {
+        warn!("fill_mode_non_solid is not supported, wireframes are drawn filled");
+        return false;
+    }
+    true
+}
+
+fn build_pipeline(device: Arc<Device>, render_pass: Arc<RenderPassAbstract + Send + Sync>, shaders: &ShaderSet,
+                  custom: &[CustomShader], shading: Shading, state: RenderState) -> Result<Arc<ConcreteGraphicsPipeline>> {
+
+    let wireframe = wireframe(&device, shading);
+    let input = SingleBufferDefinition::<Vertex>::new();
+    let vs_entry = entry_point!(shaders.vs, vs, vertex, Vertex);
+
+    Ok(match shading {
+        Shading::Textured => build_pipeline!(device, render_pass, input, vs_entry,
+            entry_point!(shaders.fs, fs, fragment, Fragment), state, wireframe),
+        Shading::FlatColor | Shading::Wireframe => build_pipeline!(device, render_pass, input, vs_entry,
+            entry_point!(shaders.flat_fs, flat_fs, fragment, Fragment), state, wireframe),
+        Shading::VertexColor => build_pipeline!(device, render_pass, input, vs_entry,
+            entry_point!(shaders.color_fs, color_fs, fragment, Fragment), state, wireframe),
+        Shading::Custom(id) => {
+            let shader = custom.get(id.index())
+                .ok_or_else(|| Error::InvalidShader(format!("no custom shader {}", id.index())))?;
+            build_pipeline!(device, render_pass, input, shader.vertex.entry_point(),
+                shader.fragment.entry_point(), state, wireframe)
+        },
+    })
+}
+
+/// Shadings other than `Textured` share their fragment shader with the
+/// non-instanced pipelines, which also apply the per-instance tint.
+/// Custom shadings can't be drawn instanced.
+fn build_instanced_pipeline(device: Arc<Device>, render_pass: Arc<RenderPassAbstract + Send + Sync>, shaders: &ShaderSet,
+                            shading: Shading, state: RenderState) -> Result<Arc<InstancedGraphicsPipeline>> {
+
+    let wireframe = wireframe(&device, shading);
+    let input = OneVertexOneInstanceDefinition::<Vertex, InstanceData>::new();
+    let vs_entry = entry_point!(shaders.instanced_vs, instanced_vs, vertex, Vertex);
+
+    Ok(match shading {
+        Shading::Textured => build_pipeline!(device, render_pass, input, vs_entry,
+            entry_point!(shaders.instanced_fs, instanced_fs, fragment, Fragment), state, wireframe),
+        Shading::FlatColor | Shading::Wireframe => build_pipeline!(device, render_pass, input, vs_entry,
+            entry_point!(shaders.flat_fs, flat_fs, fragment, Fragment), state, wireframe),
+        Shading::VertexColor => build_pipeline!(device, render_pass, input, vs_entry,
+            entry_point!(shaders.color_fs, color_fs, fragment, Fragment), state, wireframe),
+        Shading::Custom(_) => return Err(Error::InvalidShader("custom shadings can't be drawn instanced".to_owned())),
+    })
+}
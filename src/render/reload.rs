@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use std::sync::mpsc::{ channel, Receiver };
+use std::time::Duration;
+
+use notify::{ DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher };
+use vulkano::device::Device;
+use vulkano::pipeline::shader::ShaderModule;
+
+use error::{ Error, Result };
+use render::shader::{ compile_glsl, ShaderStage };
+use render::spirv::{ self, Descriptor, DescriptorKind, Reflection, Variable, VariableType };
+
+
+/// A shader compiled into the binary. Its file in the shader directory can
+/// replace it while running, as long as the interface stays the same.
+pub struct BuiltinShader {
+    pub file: &'static str,
+    pub source: &'static str,
+    pub stage: ShaderStage,
+}
+
+pub const VERTEX_SHADER: BuiltinShader = BuiltinShader {
+    file: "vertex_shader.glsl",
+    source: include_str!("../shaders/vertex_shader.glsl"),
+    stage: ShaderStage::Vertex,
+};
+pub const FRAGMENT_SHADER: BuiltinShader = BuiltinShader {
+    file: "fragment_shader.glsl",
+    source: include_str!("../shaders/fragment_shader.glsl"),
+    stage: ShaderStage::Fragment,
+};
+pub const INSTANCED_VERTEX_SHADER: BuiltinShader = BuiltinShader {
+    file: "instanced_vertex_shader.glsl",
+    source: include_str!("../shaders/instanced_vertex_shader.glsl"),
+    stage: ShaderStage::Vertex,
+};
+pub const INSTANCED_FRAGMENT_SHADER: BuiltinShader = BuiltinShader {
+    file: "instanced_fragment_shader.glsl",
+    source: include_str!("../shaders/instanced_fragment_shader.glsl"),
+    stage: ShaderStage::Fragment,
+};
+pub const FLAT_FRAGMENT_SHADER: BuiltinShader = BuiltinShader {
+    file: "flat_fragment_shader.glsl",
+    source: include_str!("../shaders/flat_fragment_shader.glsl"),
+    stage: ShaderStage::Fragment,
+};
+pub const COLOR_FRAGMENT_SHADER: BuiltinShader = BuiltinShader {
+    file: "color_fragment_shader.glsl",
+    source: include_str!("../shaders/color_fragment_shader.glsl"),
+    stage: ShaderStage::Fragment,
+};
+
+/// Editors tend to write a file in several steps, wait for them to settle.
+const DEBOUNCE: u64 = 200;
+
+
+/// Something on disk the context has to reload.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Texture(PathBuf),
+    Shaders,
+}
+
+/// Watches the shader directory and the textures used by drawn meshes.
+/// Directories are watched rather than files, so that editors saving
+/// through a rename are still noticed.
+pub struct HotReloader {
+    watcher: RecommendedWatcher,
+    events: Receiver<DebouncedEvent>,
+    shader_dir: PathBuf,
+    textures: HashSet<PathBuf>,
+    directories: HashSet<PathBuf>,
+}
+
+impl HotReloader {
+    pub fn new(shader_dir: &Path) -> Result<Self> {
+        let (sender, events) = channel();
+        let mut watcher: RecommendedWatcher = Watcher::new(sender, Duration::from_millis(DEBOUNCE))?;
+
+        let shader_dir = shader_dir.canonicalize()?;
+        watcher.watch(&shader_dir, RecursiveMode::NonRecursive)?;
+
+        let mut directories = HashSet::new();
+        directories.insert(shader_dir.clone());
+
+        Ok(Self {
+            watcher,
+            events,
+            shader_dir,
+            textures: HashSet::new(),
+            directories,
+        })
+    }
+
+    /// Does nothing if the texture is already known, even if watching it failed.
+    pub fn watch_texture(&mut self, path: &Path) -> Result<()> {
+        if self.textures.contains(path) {
+            return Ok(());
+        }
+
+        self.textures.insert(path.to_owned());
+
+        if let Some(directory) = path.parent() {
+            if !self.directories.contains(directory) {
+                self.watcher.watch(directory, RecursiveMode::NonRecursive)?;
+                self.directories.insert(directory.to_owned());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drains the watcher, each change being reported once.
+    pub fn changes(&mut self) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        for event in self.events.try_iter() {
+            let path = match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => path,
+                DebouncedEvent::Error(err, path) => {
+                    warn!("File watcher error on {:?}: {}", path, err);
+                    continue;
+                },
+                _ => continue,
+            };
+            let path = path.canonicalize().unwrap_or(path);
+
+            let change = if self.textures.contains(&path) {
+                Change::Texture(path)
+            } else if path.parent() == Some(&self.shader_dir) && path.extension().map_or(false, |ext| ext == "glsl") {
+                Change::Shaders
+            } else {
+                continue;
+            };
+
+            if !changes.contains(&change) {
+                changes.push(change);
+            }
+        }
+
+        changes
+    }
+}
+
+/// Compiles the file replacing `shader` in `dir` and loads it on the device.
+/// Compiler messages end up in `Error::ShaderCompilation`, and a file whose
+/// inputs, outputs or uniforms differ from the built-in shader is refused
+/// with `Error::InvalidShader`: the pipelines were generated for those.
+pub fn load_shader(device: Arc<Device>, dir: &Path, shader: &BuiltinShader) -> Result<Arc<ShaderModule>> {
+    let path = dir.join(shader.file);
+    let mut source = String::new();
+    File::open(&path)?.read_to_string(&mut source)?;
+
+    let bytes = compile_glsl(&source, shader.stage, &path)?;
+    let expected = spirv::reflect(&compile_glsl(shader.source, shader.stage, Path::new(shader.file))?)?;
+    check_interface(&spirv::reflect(&bytes)?, &expected, &path)?;
+
+    Ok(unsafe { ShaderModule::new(device, &bytes)? })
+}
+
+fn check_interface(found: &Reflection, expected: &Reflection, path: &Path) -> Result<()> {
+    let mismatch = if variables(&found.inputs) != variables(&expected.inputs) {
+        Some("inputs")
+    } else if variables(&found.outputs) != variables(&expected.outputs) {
+        Some("outputs")
+    } else if descriptors(&found.descriptors) != descriptors(&expected.descriptors) {
+        Some("descriptors")
+    } else if found.push_constants != expected.push_constants {
+        Some("push constants")
+    } else {
+        None
+    };
+
+    match mismatch {
+        Some(what) => Err(Error::InvalidShader(format!(
+            "{} changes the {} of the built-in shader, restart to use it", path.display(), what))),
+        None => Ok(()),
+    }
+}
+
+/// Names don't matter to the pipelines, only locations and types.
+fn variables(variables: &[Variable]) -> Vec<(u32, VariableType)> {
+    let mut variables: Vec<_> = variables.iter().map(|variable| (variable.location, variable.ty)).collect();
+    variables.sort_by_key(|&(location, _)| location);
+    variables
+}
+
+fn descriptors(descriptors: &[Descriptor]) -> Vec<(u32, u32, DescriptorKind, u32)> {
+    let mut descriptors: Vec<_> = descriptors.iter()
+        .map(|descriptor| (descriptor.set, descriptor.binding, descriptor.kind, descriptor.array_count))
+        .collect();
+    descriptors.sort_by_key(|&(set, binding, _, _)| (set, binding));
+    descriptors
+}
@@ -0,0 +1,469 @@
+use std::borrow::Cow;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Read;
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+use std::vec;
+
+use glsl_to_spirv::{ self, ShaderType };
+use vulkano::descriptor::descriptor::{ DescriptorBufferDesc, DescriptorDesc, DescriptorDescTy, DescriptorImageDesc,
+                                       DescriptorImageDescArray, DescriptorImageDescDimensions, ShaderStages };
+use vulkano::descriptor::pipeline_layout::{ PipelineLayoutDesc, PipelineLayoutDescPcRange };
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::pipeline::shader::{ GraphicsEntryPoint, GraphicsShaderType, ShaderInterfaceDef, ShaderInterfaceDefEntry, ShaderModule };
+
+use error::{ Error, Result };
+use render::vs;
+use render::material::MaterialParams;
+use render::spirv::{ self, Descriptor, DescriptorKind, ImageDim, ScalarKind, Variable, VariableType };
+
+pub use render::spirv::{ Reflection, ShaderStage };
+
+
+/// Largest push constant block a custom shader may declare, the size every
+/// device supports.
+pub const MAX_PUSH_CONSTANTS_SIZE: usize = 128;
+
+/// Raw push constant data of a material, see `Material::with_push_constants`.
+pub type PushConstants = [u32; MAX_PUSH_CONSTANTS_SIZE / 4];
+
+#[derive(Debug, Clone)]
+pub enum ShaderSource {
+    Glsl(String),
+    SpirV(Vec<u8>),
+}
+
+impl ShaderSource {
+    /// `.spv` files are read as SPIR-V, anything else as GLSL.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+
+        if path.extension().map_or(false, |ext| ext == "spv") {
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            Ok(ShaderSource::SpirV(bytes))
+        } else {
+            let mut source = String::new();
+            file.read_to_string(&mut source)?;
+            Ok(ShaderSource::Glsl(source))
+        }
+    }
+
+    /// `origin` names the source in compiler errors.
+    pub fn to_spirv(&self, stage: ShaderStage, origin: &Path) -> Result<Vec<u8>> {
+        match *self {
+            ShaderSource::Glsl(ref source) => compile_glsl(source, stage, origin),
+            ShaderSource::SpirV(ref bytes) => Ok(bytes.clone()),
+        }
+    }
+}
+
+/// Compiles GLSL to SPIR-V. Compiler messages end up in `Error::ShaderCompilation`.
+pub fn compile_glsl(source: &str, stage: ShaderStage, origin: &Path) -> Result<Vec<u8>> {
+    let ty = match stage {
+        ShaderStage::Vertex => ShaderType::Vertex,
+        ShaderStage::Fragment => ShaderType::Fragment,
+    };
+
+    let mut spirv = glsl_to_spirv::compile(source, ty)
+        .map_err(|message| Error::ShaderCompilation(origin.to_owned(), message))?;
+    let mut bytes = Vec::new();
+    spirv.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// A shader module loaded at runtime, with the interface reflected from
+/// its SPIR-V instead of generated at build time.
+pub struct RuntimeShader {
+    module: Arc<ShaderModule>,
+    reflection: Reflection,
+    entry_point: CString,
+}
+
+impl RuntimeShader {
+    pub fn new(device: Arc<Device>, source: &ShaderSource, stage: ShaderStage) -> Result<Self> {
+        Self::from_source(device, source, stage, Path::new("<inline shader>"))
+    }
+
+    pub fn load<P: AsRef<Path>>(device: Arc<Device>, path: P, stage: ShaderStage) -> Result<Self> {
+        let source = ShaderSource::load(path.as_ref())?;
+        Self::from_source(device, &source, stage, path.as_ref())
+    }
+
+    fn from_source(device: Arc<Device>, source: &ShaderSource, stage: ShaderStage, origin: &Path) -> Result<Self> {
+        let bytes = source.to_spirv(stage, origin)?;
+        let reflection = spirv::reflect(&bytes)?;
+        if reflection.stage != stage {
+            return Err(Error::InvalidShader(format!("{} is a {:?} shader, expected a {:?} one",
+                origin.display(), reflection.stage, stage)));
+        }
+
+        let entry_point = CString::new(reflection.entry_point.clone())
+            .map_err(|_| Error::InvalidShader("entry point name contains a nul byte".to_owned()))?;
+        let module = unsafe { ShaderModule::new(device, &bytes)? };
+
+        Ok(Self {
+            module,
+            reflection,
+            entry_point,
+        })
+    }
+
+    pub fn reflection(&self) -> &Reflection {
+        &self.reflection
+    }
+
+    pub fn module(&self) -> &Arc<ShaderModule> {
+        &self.module
+    }
+
+    pub fn entry_point(&self) -> GraphicsEntryPoint<(), RuntimeInterface, RuntimeInterface, RuntimeLayout> {
+        let ty = match self.reflection.stage {
+            ShaderStage::Vertex => GraphicsShaderType::Vertex,
+            ShaderStage::Fragment => GraphicsShaderType::Fragment,
+        };
+
+        // The interface and layout were reflected from this very module.
+        unsafe {
+            self.module.graphics_entry_point(&self.entry_point,
+                RuntimeInterface(self.reflection.inputs.clone()),
+                RuntimeInterface(self.reflection.outputs.clone()),
+                RuntimeLayout::new(&self.reflection),
+                ty)
+        }
+    }
+}
+
+/// Inputs or outputs of a `RuntimeShader`.
+#[derive(Debug, Clone)]
+pub struct RuntimeInterface(Vec<Variable>);
+
+unsafe impl ShaderInterfaceDef for RuntimeInterface {
+    type Iter = vec::IntoIter<ShaderInterfaceDefEntry>;
+
+    fn elements(&self) -> Self::Iter {
+        self.0.iter()
+            .map(|variable| ShaderInterfaceDefEntry {
+                location: variable.location .. variable.location + variable.ty.columns,
+                format: format(variable.ty),
+                name: variable.name.clone().map(Cow::Owned),
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+}
+
+fn format(ty: VariableType) -> Format {
+    match (ty.kind, ty.components) {
+        (ScalarKind::Float, 1) => Format::R32Sfloat,
+        (ScalarKind::Float, 2) => Format::R32G32Sfloat,
+        (ScalarKind::Float, 3) => Format::R32G32B32Sfloat,
+        (ScalarKind::Float, _) => Format::R32G32B32A32Sfloat,
+        (ScalarKind::Int, 1) => Format::R32Sint,
+        (ScalarKind::Int, 2) => Format::R32G32Sint,
+        (ScalarKind::Int, 3) => Format::R32G32B32Sint,
+        (ScalarKind::Int, _) => Format::R32G32B32A32Sint,
+        (ScalarKind::Uint, 1) => Format::R32Uint,
+        (ScalarKind::Uint, 2) => Format::R32G32Uint,
+        (ScalarKind::Uint, 3) => Format::R32G32B32Uint,
+        (ScalarKind::Uint, _) => Format::R32G32B32A32Uint,
+    }
+}
+
+/// Descriptors and push constants of a `RuntimeShader`, all visible to
+/// its stage only.
+#[derive(Debug, Clone)]
+pub struct RuntimeLayout {
+    stages: ShaderStages,
+    descriptors: Vec<Descriptor>,
+    push_constants: Option<u32>,
+}
+
+impl RuntimeLayout {
+    fn new(reflection: &Reflection) -> Self {
+        let stages = match reflection.stage {
+            ShaderStage::Vertex => ShaderStages { vertex: true, .. ShaderStages::none() },
+            ShaderStage::Fragment => ShaderStages { fragment: true, .. ShaderStages::none() },
+        };
+
+        Self {
+            stages,
+            descriptors: reflection.descriptors.clone(),
+            push_constants: reflection.push_constants,
+        }
+    }
+}
+
+unsafe impl PipelineLayoutDesc for RuntimeLayout {
+    fn num_sets(&self) -> usize {
+        self.descriptors.iter().map(|d| d.set as usize + 1).max().unwrap_or(0)
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        if set >= self.num_sets() {
+            return None;
+        }
+        Some(self.descriptors.iter()
+            .filter(|d| d.set as usize == set)
+            .map(|d| d.binding as usize + 1)
+            .max()
+            .unwrap_or(0))
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.descriptors.iter()
+            .find(|d| d.set as usize == set && d.binding as usize == binding)
+            .map(|d| DescriptorDesc {
+                ty: descriptor_ty(d.kind),
+                array_count: d.array_count,
+                stages: self.stages,
+                readonly: match d.kind {
+                    DescriptorKind::StorageImage(_) | DescriptorKind::StorageBuffer => false,
+                    _ => true,
+                },
+            })
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        if self.push_constants.is_some() { 1 } else { 0 }
+    }
+
+    fn push_constants_range(&self, num: usize) -> Option<PipelineLayoutDescPcRange> {
+        match self.push_constants {
+            Some(size) if num == 0 => Some(PipelineLayoutDescPcRange {
+                offset: 0,
+                size: size as usize,
+                stages: self.stages,
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn descriptor_ty(kind: DescriptorKind) -> DescriptorDescTy {
+    let image = |info: spirv::ImageInfo, sampled| DescriptorImageDesc {
+        sampled,
+        dimensions: match info.dim {
+            ImageDim::Dim1d => DescriptorImageDescDimensions::OneDimensional,
+            ImageDim::Dim2d => DescriptorImageDescDimensions::TwoDimensional,
+            ImageDim::Dim3d => DescriptorImageDescDimensions::ThreeDimensional,
+            ImageDim::Cube => DescriptorImageDescDimensions::Cube,
+        },
+        format: None,
+        multisampled: info.multisampled,
+        array_layers: if info.arrayed {
+            DescriptorImageDescArray::Arrayed { max_layers: None }
+        } else {
+            DescriptorImageDescArray::NonArrayed
+        },
+    };
+
+    match kind {
+        DescriptorKind::CombinedImageSampler(info) => DescriptorDescTy::CombinedImageSampler(image(info, true)),
+        DescriptorKind::SampledImage(info) => DescriptorDescTy::Image(image(info, true)),
+        DescriptorKind::StorageImage(info) => DescriptorDescTy::Image(image(info, false)),
+        DescriptorKind::Sampler => DescriptorDescTy::Sampler,
+        DescriptorKind::UniformBuffer(_) => DescriptorDescTy::Buffer(DescriptorBufferDesc { dynamic: Some(false), storage: false }),
+        DescriptorKind::StorageBuffer => DescriptorDescTy::Buffer(DescriptorBufferDesc { dynamic: Some(false), storage: true }),
+    }
+}
+
+/// Identifies a custom shader registered with `PipelineCache::add_custom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderId(usize);
+
+impl ShaderId {
+    pub(crate) fn new(index: usize) -> Self {
+        ShaderId(index)
+    }
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A user supplied vertex and fragment shader pair, drawn through
+/// `Shading::Custom`.
+///
+/// They must follow the conventions of the built-in shaders:
+/// - vertex inputs are fields of `Vertex`, matched by name (`pos`, `uv`, `color`),
+/// - set 0 holds the material texture at binding 0, the `model`, `projection`
+///   and `view` matrices at binding 1 and the material parameters at binding 2,
+///   each declared by at least one of the shaders,
+/// - the fragment shader writes a single color at location 0,
+/// - push constants take up to `MAX_PUSH_CONSTANTS_SIZE` bytes, filled from
+///   the material.
+pub struct CustomShader {
+    pub vertex: RuntimeShader,
+    pub fragment: RuntimeShader,
+}
+
+impl CustomShader {
+    pub fn new(vertex: RuntimeShader, fragment: RuntimeShader) -> Result<Self> {
+        let shader = Self {
+            vertex,
+            fragment,
+        };
+        validate(shader.vertex.reflection(), shader.fragment.reflection())?;
+
+        Ok(shader)
+    }
+
+    pub fn load<P: AsRef<Path>>(device: Arc<Device>, vertex: P, fragment: P) -> Result<Self> {
+        Self::new(RuntimeShader::load(device.clone(), vertex, ShaderStage::Vertex)?,
+                  RuntimeShader::load(device, fragment, ShaderStage::Fragment)?)
+    }
+}
+
+/// Checks a vertex and fragment shader pair against the conventions listed
+/// on `CustomShader`.
+fn validate(vertex: &Reflection, fragment: &Reflection) -> Result<()> {
+    let invalid = |message: String| Err(Error::InvalidShader(message));
+
+    for input in vertex.inputs.iter() {
+        let expected = match input.name.as_ref().map(|name| name.as_str()) {
+            Some("pos") | Some("color") => 3,
+            Some("uv") => 2,
+            _ => return invalid(format!("vertex input at location {} is not a field of Vertex", input.location)),
+        };
+        if input.ty != (VariableType { kind: ScalarKind::Float, components: expected, columns: 1 }) {
+            return invalid(format!("vertex input at location {} should be a vec{}", input.location, expected));
+        }
+    }
+
+    if fragment.outputs.iter().any(|output| output.location != 0) {
+        return invalid("the fragment shader may only write to location 0".to_owned());
+    }
+
+    let expected = [
+        (0, "the material texture, a sampler2D"),
+        (1, "the Data uniform block"),
+        (2, "the Material uniform block"),
+    ];
+    for descriptor in vertex.descriptors.iter().chain(fragment.descriptors.iter()) {
+        let valid = descriptor.set == 0 && descriptor.array_count == 1 && match (descriptor.binding, descriptor.kind) {
+            (0, DescriptorKind::CombinedImageSampler(info)) => info.dim == ImageDim::Dim2d && !info.arrayed && !info.multisampled,
+            (1, DescriptorKind::UniformBuffer(size)) => size as usize == mem::size_of::<vs::ty::Data>(),
+            (2, DescriptorKind::UniformBuffer(size)) => size as usize == mem::size_of::<MaterialParams>(),
+            _ => false,
+        };
+        if !valid {
+            let expected = expected.iter().find(|&&(binding, _)| descriptor.set == 0 && binding == descriptor.binding);
+            return invalid(match expected {
+                Some(&(_, what)) => format!("descriptor at set {} binding {} doesn't match {}",
+                    descriptor.set, descriptor.binding, what),
+                None => format!("descriptor at set {} binding {} isn't used by the engine",
+                    descriptor.set, descriptor.binding),
+            });
+        }
+    }
+    for &(binding, what) in expected.iter() {
+        let declared = vertex.descriptors.iter().chain(fragment.descriptors.iter())
+            .any(|descriptor| descriptor.binding == binding);
+        if !declared {
+            return invalid(format!("binding {}, {}, must be declared even if unused", binding, what));
+        }
+    }
+
+    for reflection in &[vertex, fragment] {
+        if reflection.push_constants.map_or(false, |size| size as usize > MAX_PUSH_CONSTANTS_SIZE) {
+            return invalid(format!("push constants of the {:?} shader exceed {} bytes",
+                reflection.stage, MAX_PUSH_CONSTANTS_SIZE));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    const VERTEX: &str = include_str!("../shaders/vertex_shader.glsl");
+    const FRAGMENT: &str = include_str!("../shaders/fragment_shader.glsl");
+
+    fn reflect(source: &str, stage: ShaderStage) -> Reflection {
+        let bytes = compile_glsl(source, stage, Path::new("test.glsl")).unwrap();
+        spirv::reflect(&bytes).unwrap()
+    }
+
+    fn validate_sources(vertex: &str, fragment: &str) -> Result<()> {
+        validate(&reflect(vertex, ShaderStage::Vertex), &reflect(fragment, ShaderStage::Fragment))
+    }
+
+    fn error_message(vertex: &str, fragment: &str) -> String {
+        match validate_sources(vertex, fragment) {
+            Err(Error::InvalidShader(message)) => message,
+            other => panic!("expected an invalid shader, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_the_builtin_shaders() {
+        validate_sources(VERTEX, FRAGMENT).unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_vertex_inputs() {
+        let vertex = VERTEX
+            .replace("in vec3 color;", "in vec3 color;\nlayout(location = 3) in vec4 normal;")
+            .replace("v_tint = vec4(1.0);", "v_tint = normal;");
+        assert_eq!(error_message(&vertex, FRAGMENT), "vertex input at location 3 is not a field of Vertex");
+    }
+
+    #[test]
+    fn rejects_mistyped_vertex_inputs() {
+        let vertex = VERTEX
+            .replace("in vec2 uv;", "in vec3 uv;")
+            .replace("v_tex_coords = uv;", "v_tex_coords = uv.xy;");
+        assert_eq!(error_message(&vertex, FRAGMENT), "vertex input at location 1 should be a vec2");
+    }
+
+    #[test]
+    fn rejects_extra_fragment_outputs() {
+        let fragment = FRAGMENT
+            .replace("out vec4 f_color;", "out vec4 f_color;\nlayout(location = 1) out vec4 f_normal;")
+            .replace("void main() {", "void main() {\n    f_normal = vec4(0.0);");
+        assert_eq!(error_message(VERTEX, &fragment), "the fragment shader may only write to location 0");
+    }
+
+    #[test]
+    fn rejects_mismatched_descriptors() {
+        let fragment = FRAGMENT.replace("vec2 offset;", "vec2 offset;\n    vec4 extra;");
+        assert_eq!(error_message(VERTEX, &fragment),
+                   "descriptor at set 0 binding 2 doesn't match the Material uniform block");
+
+        let fragment = FRAGMENT.replace("set = 0, binding = 0", "set = 1, binding = 0");
+        assert_eq!(error_message(VERTEX, &fragment),
+                   "descriptor at set 1 binding 0 isn't used by the engine");
+    }
+
+    #[test]
+    fn rejects_missing_descriptors() {
+        let fragment = "
+            #version 450
+            layout(location = 0) out vec4 f_color;
+            layout(set = 0, binding = 2) uniform Material { vec4 base_color; vec4 tint; vec2 tiling; vec2 offset; } material;
+            void main() {
+                f_color = material.base_color;
+            }
+        ";
+        assert_eq!(error_message(VERTEX, fragment),
+                   "binding 0, the material texture, a sampler2D, must be declared even if unused");
+    }
+
+    #[test]
+    fn rejects_large_push_constants() {
+        let fragment = FRAGMENT
+            .replace("void main() {", "layout(push_constant) uniform Push { vec4 values[9]; } push;\nvoid main() {")
+            .replace("* material.tint;", "* material.tint * push.values[8];");
+        assert_eq!(error_message(VERTEX, &fragment), "push constants of the Fragment shader exceed 128 bytes");
+    }
+}
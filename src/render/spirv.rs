@@ -0,0 +1,636 @@
+//! Just enough SPIR-V parsing to reflect the interface of a vertex or
+//! fragment shader: its inputs, outputs, descriptors and push constants.
+
+use std::collections::HashMap;
+
+use error::{ Error, Result };
+
+
+const MAGIC: u32 = 0x0723_0203;
+
+const OP_NAME: u16 = 5;
+const OP_ENTRY_POINT: u16 = 15;
+const OP_TYPE_INT: u16 = 21;
+const OP_TYPE_FLOAT: u16 = 22;
+const OP_TYPE_VECTOR: u16 = 23;
+const OP_TYPE_MATRIX: u16 = 24;
+const OP_TYPE_IMAGE: u16 = 25;
+const OP_TYPE_SAMPLER: u16 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u16 = 27;
+const OP_TYPE_ARRAY: u16 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u16 = 29;
+const OP_TYPE_STRUCT: u16 = 30;
+const OP_TYPE_POINTER: u16 = 32;
+const OP_CONSTANT: u16 = 43;
+const OP_VARIABLE: u16 = 59;
+const OP_DECORATE: u16 = 71;
+const OP_MEMBER_DECORATE: u16 = 72;
+
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+const EXECUTION_MODEL_VERTEX: u32 = 0;
+const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+
+/// Types may only reference ids declared before them, but nothing stops a
+/// malformed module from making them cyclic.
+const MAX_TYPE_DEPTH: u32 = 32;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarKind {
+    Float,
+    Int,
+    Uint,
+}
+
+/// Type of an input or output: `columns` vectors of `components` 32 bit
+/// scalars, one location per column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariableType {
+    pub kind: ScalarKind,
+    pub components: u32,
+    pub columns: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: Option<String>,
+    pub location: u32,
+    pub ty: VariableType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageDim {
+    Dim1d,
+    Dim2d,
+    Dim3d,
+    Cube,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub dim: ImageDim,
+    pub arrayed: bool,
+    pub multisampled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DescriptorKind {
+    CombinedImageSampler(ImageInfo),
+    SampledImage(ImageInfo),
+    StorageImage(ImageInfo),
+    Sampler,
+    /// Size of the block in bytes.
+    UniformBuffer(u32),
+    StorageBuffer,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Descriptor {
+    pub name: Option<String>,
+    pub set: u32,
+    pub binding: u32,
+    pub kind: DescriptorKind,
+    pub array_count: u32,
+}
+
+/// Interface of a shader's entry point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reflection {
+    pub stage: ShaderStage,
+    pub entry_point: String,
+    pub inputs: Vec<Variable>,
+    pub outputs: Vec<Variable>,
+    pub descriptors: Vec<Descriptor>,
+    /// Size in bytes of the push constant block, if any.
+    pub push_constants: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+enum Type {
+    Int { signed: bool },
+    Float,
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image(ImageInfo, u32),
+    Sampler,
+    SampledImage { image: u32 },
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { pointee: u32 },
+}
+
+/// Ids and decorations collected in a single pass over the module.
+#[derive(Default)]
+struct Module {
+    names: HashMap<u32, String>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    /// `(id, decoration)` to the first literal, or 0 for flags.
+    decorations: HashMap<(u32, u32), u32>,
+    /// `(struct, member, decoration)` to the first literal.
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+    /// `(pointer type, id, storage class)`.
+    variables: Vec<(u32, u32, u32)>,
+    entry_point: Option<(ShaderStage, String, Vec<u32>)>,
+}
+
+/// Reflects the first vertex or fragment entry point of a SPIR-V module.
+pub fn reflect(bytes: &[u8]) -> Result<Reflection> {
+    let words = words(bytes)?;
+    let module = parse(&words)?;
+
+    let (stage, entry_point, interface) = module.entry_point.clone()
+        .ok_or_else(|| invalid("no vertex or fragment entry point"))?;
+
+    let mut reflection = Reflection {
+        stage,
+        entry_point,
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        descriptors: Vec::new(),
+        push_constants: None,
+    };
+
+    for &(pointer, id, storage) in module.variables.iter() {
+        let pointee = match module.types.get(&pointer) {
+            Some(&Type::Pointer { pointee }) => pointee,
+            _ => return Err(invalid("variable without a pointer type")),
+        };
+        let name = module.names.get(&id).cloned();
+
+        match storage {
+            STORAGE_INPUT | STORAGE_OUTPUT => {
+                // Only variables of this entry point with a location, which
+                // leaves out built-ins like gl_Position.
+                let location = match module.decorations.get(&(id, DECORATION_LOCATION)) {
+                    Some(&location) if interface.contains(&id) => location,
+                    _ => continue,
+                };
+                let variable = Variable {
+                    name,
+                    location,
+                    ty: module.variable_type(pointee, 0)?,
+                };
+                if storage == STORAGE_INPUT {
+                    reflection.inputs.push(variable);
+                } else {
+                    reflection.outputs.push(variable);
+                }
+            },
+            STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {
+                let (set, binding) = match (module.decorations.get(&(id, DECORATION_DESCRIPTOR_SET)),
+                                            module.decorations.get(&(id, DECORATION_BINDING))) {
+                    (Some(&set), Some(&binding)) => (set, binding),
+                    _ => continue,
+                };
+                let (ty, array_count) = module.unwrap_array(pointee)?;
+                reflection.descriptors.push(Descriptor {
+                    name,
+                    set,
+                    binding,
+                    kind: module.descriptor_kind(ty, storage)?,
+                    array_count,
+                });
+            },
+            STORAGE_PUSH_CONSTANT => {
+                reflection.push_constants = Some(module.size_of(pointee, 0)?);
+            },
+            _ => {},
+        }
+    }
+
+    reflection.inputs.sort_by_key(|variable| variable.location);
+    reflection.outputs.sort_by_key(|variable| variable.location);
+    reflection.descriptors.sort_by_key(|descriptor| (descriptor.set, descriptor.binding));
+
+    Ok(reflection)
+}
+
+fn invalid(message: &str) -> Error {
+    Error::InvalidShader(format!("malformed SPIR-V ({})", message))
+}
+
+fn words(bytes: &[u8]) -> Result<Vec<u32>> {
+    if bytes.len() % 4 != 0 || bytes.len() < 20 {
+        return Err(invalid("truncated module"));
+    }
+
+    let mut words: Vec<u32> = bytes.chunks(4)
+        .map(|b| u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24)
+        .collect();
+
+    if words[0] == MAGIC.swap_bytes() {
+        for word in words.iter_mut() {
+            *word = word.swap_bytes();
+        }
+    }
+    if words[0] != MAGIC {
+        return Err(invalid("bad magic number"));
+    }
+
+    Ok(words)
+}
+
+/// Decodes a nul terminated literal string, returning it and how many
+/// words it took.
+fn string(words: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, &word) in words.iter().enumerate() {
+        for shift in &[0, 8, 16, 24] {
+            let byte = (word >> shift) as u8;
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1);
+            }
+            bytes.push(byte);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+fn tail(operands: &[u32], start: usize) -> &[u32] {
+    operands.get(start..).unwrap_or(&[])
+}
+
+fn parse(words: &[u32]) -> Result<Module> {
+    let mut module = Module::default();
+
+    // Skips the header: magic, version, generator, bound and schema.
+    let mut rest = &words[5..];
+    while !rest.is_empty() {
+        let count = (rest[0] >> 16) as usize;
+        let opcode = rest[0] as u16;
+        if count == 0 || count > rest.len() {
+            return Err(invalid("truncated instruction"));
+        }
+        let operands = &rest[1..count];
+        rest = &rest[count..];
+
+        let operand = |i: usize| operands.get(i).cloned().ok_or_else(|| invalid("missing operand"));
+
+        match opcode {
+            OP_NAME => {
+                let (name, _) = string(tail(operands, 1));
+                if !name.is_empty() {
+                    module.names.insert(operand(0)?, name);
+                }
+            },
+            OP_ENTRY_POINT => {
+                if module.entry_point.is_some() {
+                    continue;
+                }
+                let stage = match operand(0)? {
+                    EXECUTION_MODEL_VERTEX => ShaderStage::Vertex,
+                    EXECUTION_MODEL_FRAGMENT => ShaderStage::Fragment,
+                    _ => continue,
+                };
+                let (name, len) = string(tail(operands, 2));
+                let interface = tail(operands, 2 + len).to_vec();
+                module.entry_point = Some((stage, name, interface));
+            },
+            OP_TYPE_INT => {
+                module.types.insert(operand(0)?, Type::Int { signed: operand(2)? != 0 });
+            },
+            OP_TYPE_FLOAT => {
+                module.types.insert(operand(0)?, Type::Float);
+            },
+            OP_TYPE_VECTOR => {
+                module.types.insert(operand(0)?, Type::Vector { component: operand(1)?, count: operand(2)? });
+            },
+            OP_TYPE_MATRIX => {
+                module.types.insert(operand(0)?, Type::Matrix { column: operand(1)?, count: operand(2)? });
+            },
+            OP_TYPE_IMAGE => {
+                let dim = match operand(2)? {
+                    0 => ImageDim::Dim1d,
+                    1 => ImageDim::Dim2d,
+                    2 => ImageDim::Dim3d,
+                    3 => ImageDim::Cube,
+                    _ => return Err(invalid("unsupported image dimensions")),
+                };
+                let info = ImageInfo {
+                    dim,
+                    arrayed: operand(4)? != 0,
+                    multisampled: operand(5)? != 0,
+                };
+                module.types.insert(operand(0)?, Type::Image(info, operand(6)?));
+            },
+            OP_TYPE_SAMPLER => {
+                module.types.insert(operand(0)?, Type::Sampler);
+            },
+            OP_TYPE_SAMPLED_IMAGE => {
+                module.types.insert(operand(0)?, Type::SampledImage { image: operand(1)? });
+            },
+            OP_TYPE_ARRAY => {
+                module.types.insert(operand(0)?, Type::Array { element: operand(1)?, length: operand(2)? });
+            },
+            OP_TYPE_RUNTIME_ARRAY => {
+                module.types.insert(operand(0)?, Type::RuntimeArray { element: operand(1)? });
+            },
+            OP_TYPE_STRUCT => {
+                module.types.insert(operand(0)?, Type::Struct { members: tail(operands, 1).to_vec() });
+            },
+            OP_TYPE_POINTER => {
+                module.types.insert(operand(0)?, Type::Pointer { pointee: operand(2)? });
+            },
+            OP_CONSTANT => {
+                // Only 32 bit constants matter, for array lengths.
+                if operands.len() == 3 {
+                    module.constants.insert(operand(1)?, operand(2)?);
+                }
+            },
+            OP_VARIABLE => {
+                module.variables.push((operand(0)?, operand(1)?, operand(2)?));
+            },
+            OP_DECORATE => {
+                module.decorations.insert((operand(0)?, operand(1)?), operand(2).unwrap_or(0));
+            },
+            OP_MEMBER_DECORATE => {
+                module.member_decorations.insert((operand(0)?, operand(1)?, operand(2)?), operand(3).unwrap_or(0));
+            },
+            _ => {},
+        }
+    }
+
+    Ok(module)
+}
+
+impl Module {
+    fn get(&self, id: u32) -> Result<&Type> {
+        self.types.get(&id).ok_or_else(|| invalid("unknown type"))
+    }
+
+    fn scalar_kind(&self, id: u32) -> Result<ScalarKind> {
+        match *self.get(id)? {
+            Type::Float => Ok(ScalarKind::Float),
+            Type::Int { signed: true } => Ok(ScalarKind::Int),
+            Type::Int { signed: false } => Ok(ScalarKind::Uint),
+            _ => Err(Error::InvalidShader("only scalar, vector and matrix inputs and outputs are supported".to_owned())),
+        }
+    }
+
+    fn variable_type(&self, id: u32, depth: u32) -> Result<VariableType> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(invalid("types nested too deeply"));
+        }
+
+        match *self.get(id)? {
+            Type::Vector { count, .. } if count > 4 => Err(invalid("vector with more than 4 components")),
+            Type::Vector { component, count } => Ok(VariableType {
+                kind: self.scalar_kind(component)?,
+                components: count,
+                columns: 1,
+            }),
+            Type::Matrix { column, count } => Ok(VariableType {
+                columns: count,
+                .. self.variable_type(column, depth + 1)?
+            }),
+            _ => Ok(VariableType {
+                kind: self.scalar_kind(id)?,
+                components: 1,
+                columns: 1,
+            }),
+        }
+    }
+
+    /// The element type of an array and its length, 1 for anything else.
+    fn unwrap_array(&self, id: u32) -> Result<(u32, u32)> {
+        match *self.get(id)? {
+            Type::Array { element, length } => {
+                let length = self.constants.get(&length).cloned()
+                    .ok_or_else(|| invalid("array length is not a constant"))?;
+                Ok((element, length))
+            },
+            Type::RuntimeArray { .. } => Err(Error::InvalidShader("unsized descriptor arrays are not supported".to_owned())),
+            _ => Ok((id, 1)),
+        }
+    }
+
+    fn descriptor_kind(&self, id: u32, storage: u32) -> Result<DescriptorKind> {
+        let is_block = |flag| self.decorations.contains_key(&(id, flag));
+
+        match *self.get(id)? {
+            Type::SampledImage { image } => match *self.get(image)? {
+                Type::Image(info, _) => Ok(DescriptorKind::CombinedImageSampler(info)),
+                _ => Err(invalid("sampled image of a non-image type")),
+            },
+            Type::Image(info, 2) => Ok(DescriptorKind::StorageImage(info)),
+            Type::Image(info, _) => Ok(DescriptorKind::SampledImage(info)),
+            Type::Sampler => Ok(DescriptorKind::Sampler),
+            Type::Struct { .. } if storage == STORAGE_STORAGE_BUFFER || is_block(DECORATION_BUFFER_BLOCK) =>
+                Ok(DescriptorKind::StorageBuffer),
+            Type::Struct { .. } if is_block(DECORATION_BLOCK) =>
+                Ok(DescriptorKind::UniformBuffer(self.size_of(id, 0)?)),
+            _ => Err(Error::InvalidShader("unsupported descriptor type".to_owned())),
+        }
+    }
+
+    /// Size in bytes of a type laid out in a block, from its offset and
+    /// stride decorations.
+    fn size_of(&self, id: u32, depth: u32) -> Result<u32> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(invalid("types nested too deeply"));
+        }
+        let overflow = || invalid("type size overflows");
+
+        match *self.get(id)? {
+            Type::Int { .. } | Type::Float => Ok(4),
+            Type::Vector { component, count } =>
+                self.size_of(component, depth + 1)?.checked_mul(count).ok_or_else(overflow),
+            Type::Matrix { column, count } =>
+                self.size_of(column, depth + 1)?.max(16).checked_mul(count).ok_or_else(overflow),
+            Type::Array { element, length } => {
+                let length = self.constants.get(&length).cloned()
+                    .ok_or_else(|| invalid("array length is not a constant"))?;
+                let stride = match self.decorations.get(&(id, DECORATION_ARRAY_STRIDE)) {
+                    Some(&stride) => stride,
+                    None => self.size_of(element, depth + 1)?,
+                };
+                stride.checked_mul(length).ok_or_else(overflow)
+            },
+            Type::Struct { ref members } => {
+                let mut size = 0;
+                for (i, &member) in members.iter().enumerate() {
+                    let i = i as u32;
+                    let offset = self.member_decorations.get(&(id, i, DECORATION_OFFSET)).cloned().unwrap_or(size);
+                    let member_size = match (self.get(member)?, self.member_decorations.get(&(id, i, DECORATION_MATRIX_STRIDE))) {
+                        (&Type::Matrix { count, .. }, Some(&stride)) => stride.checked_mul(count).ok_or_else(overflow)?,
+                        _ => self.size_of(member, depth + 1)?,
+                    };
+                    size = size.max(offset.checked_add(member_size).ok_or_else(overflow)?);
+                }
+                Ok(size)
+            },
+            _ => Err(Error::InvalidShader("unsupported type in a uniform block".to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use render::shader::compile_glsl;
+    use super::*;
+
+    fn compile(source: &str, stage: ShaderStage) -> Reflection {
+        let bytes = compile_glsl(source, stage, Path::new("test.glsl")).unwrap();
+        reflect(&bytes).unwrap()
+    }
+
+    /// Assembles a module from `(opcode, operands)` instructions.
+    fn assemble(instructions: &[(u16, &[u32])]) -> Vec<u8> {
+        let mut words = vec![MAGIC, 0x0001_0000, 0, 100, 0];
+        for &(opcode, operands) in instructions {
+            words.push((operands.len() as u32 + 1) << 16 | u32::from(opcode));
+            words.extend_from_slice(operands);
+        }
+        words.iter().flat_map(|word| (0..4).map(move |i| (word >> (i * 8)) as u8)).collect()
+    }
+
+    /// `OpEntryPoint Fragment %1 "main"`.
+    const ENTRY_POINT: (u16, &[u32]) = (OP_ENTRY_POINT, &[EXECUTION_MODEL_FRAGMENT, 1, 0x6e69_616d, 0]);
+
+    fn error_message(bytes: &[u8]) -> String {
+        match reflect(bytes) {
+            Err(Error::InvalidShader(message)) => message,
+            other => panic!("expected an invalid shader, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reflects_vertex_inputs_and_outputs() {
+        let reflection = compile("
+            #version 450
+            layout(location = 1) in vec2 uv;
+            layout(location = 0) in vec3 pos;
+            layout(location = 2) in ivec4 ids;
+            layout(location = 3) in mat4 model;
+            layout(location = 0) out vec2 v_uv;
+            void main() {
+                v_uv = uv + float(ids.x);
+                gl_Position = model * vec4(pos, 1.0);
+            }
+        ", ShaderStage::Vertex);
+
+        assert_eq!(reflection.stage, ShaderStage::Vertex);
+        assert_eq!(reflection.entry_point, "main");
+
+        let inputs: Vec<_> = reflection.inputs.iter()
+            .map(|input| (input.name.clone().unwrap(), input.location, input.ty))
+            .collect();
+        let float = |components, columns| VariableType { kind: ScalarKind::Float, components, columns };
+        assert_eq!(inputs, vec![
+            ("pos".to_owned(), 0, float(3, 1)),
+            ("uv".to_owned(), 1, float(2, 1)),
+            ("ids".to_owned(), 2, VariableType { kind: ScalarKind::Int, components: 4, columns: 1 }),
+            ("model".to_owned(), 3, float(4, 4)),
+        ]);
+
+        // gl_Position has no location and is left out.
+        assert_eq!(reflection.outputs.len(), 1);
+        assert_eq!(reflection.outputs[0].location, 0);
+    }
+
+    #[test]
+    fn reflects_descriptors_and_push_constants() {
+        let reflection = compile("
+            #version 450
+            layout(set = 0, binding = 2) uniform Block {
+                mat4 transform;
+                vec3 color;
+                float scale[4];
+            } block;
+            layout(set = 1, binding = 0) uniform sampler2D textures[3];
+            layout(set = 0, binding = 0) buffer Storage { float values[]; } storage;
+            layout(push_constant) uniform Push { vec4 a; vec2 b; } push;
+            layout(location = 0) out vec4 f_color;
+            void main() {
+                f_color = block.transform * vec4(block.color, block.scale[1]) + texture(textures[2], push.b)
+                    + push.a * storage.values[0];
+            }
+        ", ShaderStage::Fragment);
+
+        let descriptors: Vec<_> = reflection.descriptors.iter()
+            .map(|descriptor| (descriptor.set, descriptor.binding, descriptor.kind, descriptor.array_count))
+            .collect();
+        let image = ImageInfo { dim: ImageDim::Dim2d, arrayed: false, multisampled: false };
+        assert_eq!(descriptors, vec![
+            (0, 0, DescriptorKind::StorageBuffer, 1),
+            // std140: 64 bytes of matrix, a vec3 and an array with a 16 byte stride.
+            (0, 2, DescriptorKind::UniformBuffer(64 + 16 + 4 * 16), 1),
+            (1, 0, DescriptorKind::CombinedImageSampler(image), 3),
+        ]);
+        assert_eq!(reflection.push_constants, Some(24));
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = assemble(&[ENTRY_POINT]);
+        bytes[0] = 0;
+        assert!(error_message(&bytes).contains("bad magic number"));
+    }
+
+    #[test]
+    fn rejects_truncated_modules() {
+        let bytes = assemble(&[ENTRY_POINT]);
+        assert!(error_message(&bytes[..bytes.len() - 2]).contains("truncated module"));
+        assert!(error_message(&bytes[..16]).contains("truncated module"));
+        assert!(error_message(&bytes[..bytes.len() - 4]).contains("truncated instruction"));
+    }
+
+    #[test]
+    fn rejects_unsized_descriptor_arrays() {
+        let bytes = assemble(&[
+            ENTRY_POINT,
+            (OP_DECORATE, &[5, DECORATION_DESCRIPTOR_SET, 0]),
+            (OP_DECORATE, &[5, DECORATION_BINDING, 0]),
+            (OP_TYPE_SAMPLER, &[2]),
+            (OP_TYPE_RUNTIME_ARRAY, &[3, 2]),
+            (OP_TYPE_POINTER, &[4, STORAGE_UNIFORM_CONSTANT, 3]),
+            (OP_VARIABLE, &[4, 5, STORAGE_UNIFORM_CONSTANT]),
+        ]);
+        assert!(error_message(&bytes).contains("unsized descriptor arrays"));
+    }
+
+    #[test]
+    fn rejects_cyclic_types() {
+        let bytes = assemble(&[
+            ENTRY_POINT,
+            (OP_TYPE_STRUCT, &[2, 2]),
+            (OP_TYPE_POINTER, &[3, STORAGE_PUSH_CONSTANT, 2]),
+            (OP_VARIABLE, &[3, 4, STORAGE_PUSH_CONSTANT]),
+        ]);
+        assert!(error_message(&bytes).contains("nested too deeply"));
+
+        let bytes = assemble(&[
+            (OP_ENTRY_POINT, &[EXECUTION_MODEL_FRAGMENT, 1, 0x6e69_616d, 0, 4]),
+            (OP_DECORATE, &[4, DECORATION_LOCATION, 0]),
+            (OP_TYPE_MATRIX, &[2, 2, 4]),
+            (OP_TYPE_POINTER, &[3, STORAGE_INPUT, 2]),
+            (OP_VARIABLE, &[3, 4, STORAGE_INPUT]),
+        ]);
+        assert!(error_message(&bytes).contains("nested too deeply"));
+    }
+}
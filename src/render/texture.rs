@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, RwLock };
+
+use vulkano;
+use vulkano::device::{ Device, Queue };
+use vulkano::format::R8G8B8A8Srgb;
+use vulkano::image::ImmutableImage;
+use vulkano::sampler::Sampler;
+
+use error::Result;
+use render::mesh::{ create_texture, load_texture };
+
+
+pub type TextureImage = ImmutableImage<R8G8B8A8Srgb>;
+
+struct TextureData {
+    path: PathBuf,
+    image: RwLock<Arc<TextureImage>>,
+    sampler: Arc<Sampler>,
+}
+
+/// Cheap, reference counted reference to a texture. Clones share the same
+/// GPU image, which lives as long as any handle or the cache holds it.
+#[derive(Clone)]
+pub struct TextureHandle(Arc<TextureData>);
+
+impl TextureHandle {
+    pub fn new(path: PathBuf, image: Arc<TextureImage>, sampler: Arc<Sampler>) -> Self {
+        TextureHandle(Arc::new(TextureData {
+            path,
+            image: RwLock::new(image),
+            sampler,
+        }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0.path
+    }
+
+    pub fn image(&self) -> Arc<TextureImage> {
+        self.0.image.read().expect("Texture lock poisoned").clone()
+    }
+
+    pub fn sampler(&self) -> Arc<Sampler> {
+        self.0.sampler.clone()
+    }
+
+    /// Swaps the image for every holder of this texture, e.g. on hot reload.
+    pub fn replace_image(&self, image: Arc<TextureImage>) {
+        *self.0.image.write().expect("Texture lock poisoned") = image;
+    }
+
+    /// Number of handles to this texture, the cache's included.
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
+    pub fn ptr_eq(&self, other: &TextureHandle) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Loads each texture once, keyed by canonical path, and hands out shared
+/// `TextureHandle`s. All textures share one linear, repeating sampler.
+pub struct TextureCache {
+    textures: HashMap<PathBuf, TextureHandle>,
+    sampler: Arc<Sampler>,
+    queue: Arc<Queue>,
+}
+
+impl TextureCache {
+    pub fn new(device: Arc<Device>, queue: Arc<Queue>) -> Result<Self> {
+        let sampler = vulkano::sampler::Sampler::new(device, vulkano::sampler::Filter::Linear,
+                                                    vulkano::sampler::Filter::Linear, vulkano::sampler::MipmapMode::Nearest,
+                                                    vulkano::sampler::SamplerAddressMode::Repeat,
+                                                    vulkano::sampler::SamplerAddressMode::Repeat,
+                                                    vulkano::sampler::SamplerAddressMode::Repeat,
+                                                    0.0, 1.0, 0.0, 0.0)?;
+
+        Ok(Self {
+            textures: HashMap::new(),
+            sampler,
+            queue,
+        })
+    }
+
+    /// Returns the cached texture, loading it from disk the first time.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<TextureHandle> {
+        let path = canonical(path.as_ref());
+        if let Some(handle) = self.textures.get(&path) {
+            return Ok(handle.clone());
+        }
+
+        let image = load_texture(&path, self.queue.clone())?;
+        let handle = TextureHandle::new(path.clone(), image, self.sampler.clone());
+        self.textures.insert(path, handle.clone());
+        debug!("Loaded texture {}", handle.path().display());
+
+        Ok(handle)
+    }
+
+    /// A 1x1 texture of a single color, not cached and not backed by a file.
+    pub fn solid(&self, color: [u8; 4]) -> Result<TextureHandle> {
+        let image = create_texture(color.to_vec(), 1, 1, self.queue.clone())?;
+        Ok(TextureHandle::new(PathBuf::new(), image, self.sampler.clone()))
+    }
+
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<TextureHandle> {
+        self.textures.get(&canonical(path.as_ref())).cloned()
+    }
+
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.textures.contains_key(&canonical(path.as_ref()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Decodes a cached texture from disk again and swaps it in for every
+    /// handle. Returns `false` if the texture isn't cached.
+    pub fn reload<P: AsRef<Path>>(&mut self, path: P) -> Result<bool> {
+        let handle = match self.get(path) {
+            Some(handle) => handle,
+            None => return Ok(false),
+        };
+
+        let image = load_texture(handle.path(), self.queue.clone())?;
+        handle.replace_image(image);
+
+        Ok(true)
+    }
+
+    /// Drops the cache's reference. Outstanding handles stay valid, the
+    /// next `load` decodes the file again.
+    pub fn evict<P: AsRef<Path>>(&mut self, path: P) -> bool {
+        self.textures.remove(&canonical(path.as_ref())).is_some()
+    }
+
+    /// Evicts every texture no handle refers to anymore, returning how many.
+    pub fn evict_unused(&mut self) -> usize {
+        let len = self.textures.len();
+        self.textures.retain(|_, handle| handle.ref_count() > 1);
+        len - self.textures.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
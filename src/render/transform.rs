@@ -55,4 +55,9 @@ impl Transform {
         Matrix4::from_translation(self.position)
     }
 
+    /// Model matrix, as used when drawing a mesh.
+    pub fn model_matrix(&self) -> Matrix4<f32> {
+        self.scale * self.translation_matrix()
+    }
+
 }
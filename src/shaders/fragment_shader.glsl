@@ -1,13 +1,23 @@
 #version 450
 
 layout(location = 1) in vec2 v_tex_coords;
+layout(location = 2) in vec4 v_tint;
+layout(location = 3) in vec3 v_color;
 
 layout(location = 0) out vec4 f_color;
 
 
 layout(set = 0, binding = 0) uniform sampler2D tex;
 
+layout(set = 0, binding = 2) uniform Material {
+    vec4 base_color;
+    vec4 tint;
+    vec2 tiling;
+    vec2 offset;
+} material;
+
 
 void main() {
-    f_color = texture(tex, v_tex_coords);
+    vec2 uv = v_tex_coords * material.tiling + material.offset;
+    f_color = texture(tex, uv) * material.base_color * material.tint;
 }
@@ -0,0 +1,31 @@
+#version 450
+
+layout(location = 0) in vec3 pos;
+layout(location = 1) in vec2 uv;
+layout(location = 2) in vec3 color;
+
+layout(location = 3) in vec4 model_0;
+layout(location = 4) in vec4 model_1;
+layout(location = 5) in vec4 model_2;
+layout(location = 6) in vec4 model_3;
+layout(location = 7) in vec4 tint;
+
+
+layout(location = 1) out vec2 v_tex_coords;
+layout(location = 2) out vec4 v_tint;
+layout(location = 3) out vec3 v_color;
+
+
+layout(set = 0, binding = 1) uniform Data {
+    mat4 projection;
+    mat4 view;
+} uniforms;
+
+
+void main() {
+    mat4 model = mat4(model_0, model_1, model_2, model_3);
+    gl_Position = uniforms.projection * uniforms.view * model * vec4(pos, 1.0);
+    v_tex_coords = uv;
+    v_tint = tint;
+    v_color = color;
+}
@@ -2,9 +2,12 @@
 
 layout(location = 0) in vec3 pos;
 layout(location = 1) in vec2 uv;
+layout(location = 2) in vec3 color;
 
 
 layout(location = 1) out vec2 v_tex_coords;
+layout(location = 2) out vec4 v_tint;
+layout(location = 3) out vec3 v_color;
 
 
 layout(set = 0, binding = 1) uniform Data {
@@ -18,4 +21,6 @@ void main() {
     mat4 mvp =  uniforms.projection * uniforms.view * uniforms.model;
     gl_Position =  mvp * vec4(pos, 1.0);
     v_tex_coords = uv;
+    v_tint = vec4(1.0);
+    v_color = color;
 }
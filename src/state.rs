@@ -0,0 +1,363 @@
+use render::Context;
+use event::EventHandler;
+use input::InputEvent;
+
+
+/// What a state asks the stack to do after one of its callbacks.
+pub enum Transition {
+    None,
+    /// Pauses the current state and puts a new one on top of it.
+    Push(Box<GameState>),
+    /// Removes the current state, resuming the one below. Popping the
+    /// last state quits the application.
+    Pop,
+    /// Swaps the current state for another one.
+    Replace(Box<GameState>),
+    /// Empties the stack and quits the application.
+    Quit,
+}
+
+/// One screen of the game: title screen, level, pause menu, loading screen...
+///
+/// Only the state on top of the stack receives input, and only its
+/// transitions are applied.
+pub trait GameState {
+    fn update(&mut self, _ctx: &mut Context, _dt: f32) -> Transition;
+
+    fn draw(&mut self, _ctx: &mut Context, _alpha: f32);
+
+    fn input_event(&mut self, _ctx: &mut Context, _event: &InputEvent) -> Transition {
+        Transition::None
+    }
+
+    /// Called when the state is pushed on the stack.
+    fn on_enter(&mut self, _ctx: &mut Context) {}
+
+    /// Called when the state is removed from the stack.
+    fn on_exit(&mut self, _ctx: &mut Context) {}
+
+    /// Called when another state is pushed on top of this one.
+    fn on_pause(&mut self, _ctx: &mut Context) {}
+
+    /// Called when this state is back on top.
+    fn on_resume(&mut self, _ctx: &mut Context) {}
+
+    /// Whether the states below keep updating while this one is on top.
+    fn update_below(&self) -> bool {
+        false
+    }
+
+    /// Whether the states below are drawn before this one, e.g. for an
+    /// overlay such as a pause menu.
+    fn draw_below(&self) -> bool {
+        false
+    }
+}
+
+/// Runs a stack of `GameState`s. Hand it to `Application::run` like any
+/// other `EventHandler`.
+pub struct StateStack {
+    states: Vec<Box<GameState>>,
+}
+
+impl StateStack {
+    pub fn new() -> Self {
+        Self {
+            states: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn push(&mut self, ctx: &mut Context, mut state: Box<GameState>) {
+        if let Some(top) = self.states.last_mut() {
+            top.on_pause(ctx);
+        }
+        state.on_enter(ctx);
+        self.states.push(state);
+    }
+
+    pub fn pop(&mut self, ctx: &mut Context) -> Option<Box<GameState>> {
+        let mut state = self.states.pop()?;
+        state.on_exit(ctx);
+
+        if let Some(top) = self.states.last_mut() {
+            top.on_resume(ctx);
+        }
+        Some(state)
+    }
+
+    pub fn replace(&mut self, ctx: &mut Context, mut state: Box<GameState>) -> Option<Box<GameState>> {
+        let previous = self.states.pop().map(|mut previous| {
+            previous.on_exit(ctx);
+            previous
+        });
+        state.on_enter(ctx);
+        self.states.push(state);
+        previous
+    }
+
+    /// Pops every state, top first.
+    pub fn clear(&mut self, ctx: &mut Context) {
+        while let Some(mut state) = self.states.pop() {
+            state.on_exit(ctx);
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) {
+        match transition {
+            Transition::None => {},
+            Transition::Push(state) => self.push(ctx, state),
+            Transition::Pop => {
+                self.pop(ctx);
+            },
+            Transition::Replace(state) => {
+                self.replace(ctx, state);
+            },
+            Transition::Quit => self.clear(ctx),
+        }
+
+        if self.states.is_empty() {
+            ctx.quit();
+        }
+    }
+
+    /// Walks down from the top as long as each state lets the one below it run.
+    fn lowest_active<F: Fn(&GameState) -> bool>(&self, keep_below: F) -> usize {
+        let mut index = self.states.len() - 1;
+        while index > 0 && keep_below(&*self.states[index]) {
+            index -= 1;
+        }
+        index
+    }
+}
+
+impl Default for StateStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventHandler for StateStack {
+    fn update(&mut self, ctx: &mut Context, dt: f32) {
+        if self.states.is_empty() {
+            return;
+        }
+
+        let top = self.states.len() - 1;
+        let lowest = self.lowest_active(|state| state.update_below());
+
+        let mut transition = Transition::None;
+        for (index, state) in self.states[lowest ..].iter_mut().enumerate() {
+            let result = state.update(ctx, dt);
+            if lowest + index == top {
+                transition = result;
+            }
+        }
+
+        self.apply(ctx, transition);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, alpha: f32) {
+        if self.states.is_empty() {
+            return;
+        }
+
+        let lowest = self.lowest_active(|state| state.draw_below());
+        for state in self.states[lowest ..].iter_mut() {
+            state.draw(ctx, alpha);
+        }
+    }
+
+    fn input_event(&mut self, ctx: &mut Context, event: &InputEvent) {
+        let transition = match self.states.last_mut() {
+            Some(top) => top.input_event(ctx, event),
+            None => return,
+        };
+
+        self.apply(ctx, transition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use render::config::Config;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// Logs every callback as `"<name> <callback>"` and returns
+    /// `transition` from its first update.
+    struct Logged {
+        name: &'static str,
+        log: Log,
+        transition: Option<Transition>,
+        update_below: bool,
+        draw_below: bool,
+    }
+
+    impl Logged {
+        fn new(name: &'static str, log: &Log) -> Self {
+            Self {
+                name,
+                log: log.clone(),
+                transition: None,
+                update_below: false,
+                draw_below: false,
+            }
+        }
+
+        fn record(&self, callback: &str) {
+            self.log.borrow_mut().push(format!("{} {}", self.name, callback));
+        }
+    }
+
+    impl GameState for Logged {
+        fn update(&mut self, _ctx: &mut Context, _dt: f32) -> Transition {
+            self.record("update");
+            self.transition.take().unwrap_or(Transition::None)
+        }
+
+        fn draw(&mut self, _ctx: &mut Context, _alpha: f32) {
+            self.record("draw");
+        }
+
+        fn on_enter(&mut self, _ctx: &mut Context) {
+            self.record("enter");
+        }
+
+        fn on_exit(&mut self, _ctx: &mut Context) {
+            self.record("exit");
+        }
+
+        fn on_pause(&mut self, _ctx: &mut Context) {
+            self.record("pause");
+        }
+
+        fn on_resume(&mut self, _ctx: &mut Context) {
+            self.record("resume");
+        }
+
+        fn update_below(&self) -> bool {
+            self.update_below
+        }
+
+        fn draw_below(&self) -> bool {
+            self.draw_below
+        }
+    }
+
+    /// Needs a Vulkan driver; a CPU one such as lavapipe is enough.
+    fn context() -> Context {
+        Context::new_headless(Config {
+            width: 4,
+            height: 4,
+            headless: true,
+            .. Config::default()
+        }).expect("state stack tests need a Vulkan driver")
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        log.borrow_mut().drain(..).collect()
+    }
+
+    #[test]
+    fn push_pop_and_replace() {
+        let mut ctx = context();
+        let log = Log::default();
+        let mut stack = StateStack::new();
+
+        stack.push(&mut ctx, Box::new(Logged::new("level", &log)));
+        stack.push(&mut ctx, Box::new(Logged::new("pause", &log)));
+        assert_eq!(take(&log), ["level enter", "level pause", "pause enter"]);
+
+        stack.replace(&mut ctx, Box::new(Logged::new("options", &log)));
+        assert_eq!(take(&log), ["pause exit", "options enter"]);
+        assert_eq!(stack.len(), 2);
+
+        stack.pop(&mut ctx);
+        assert_eq!(take(&log), ["options exit", "level resume"]);
+
+        stack.clear(&mut ctx);
+        assert_eq!(take(&log), ["level exit"]);
+        assert!(stack.pop(&mut ctx).is_none());
+    }
+
+    #[test]
+    fn only_the_top_state_runs_by_default() {
+        let mut ctx = context();
+        let log = Log::default();
+        let mut stack = StateStack::new();
+        stack.push(&mut ctx, Box::new(Logged::new("level", &log)));
+        stack.push(&mut ctx, Box::new(Logged::new("menu", &log)));
+        take(&log);
+
+        stack.update(&mut ctx, 0.1);
+        stack.draw(&mut ctx, 0.0);
+        assert_eq!(take(&log), ["menu update", "menu draw"]);
+    }
+
+    #[test]
+    fn overlays_let_states_below_run_bottom_first() {
+        let mut ctx = context();
+        let log = Log::default();
+        let mut stack = StateStack::new();
+        stack.push(&mut ctx, Box::new(Logged::new("world", &log)));
+        stack.push(&mut ctx, Box::new(Logged::new("level", &log)));
+        stack.push(&mut ctx, Box::new(Logged {
+            update_below: true,
+            draw_below: true,
+            .. Logged::new("hud", &log)
+        }));
+        take(&log);
+
+        stack.update(&mut ctx, 0.1);
+        stack.draw(&mut ctx, 0.0);
+        assert_eq!(take(&log), ["level update", "hud update", "level draw", "hud draw"]);
+    }
+
+    #[test]
+    fn transitions_of_states_below_are_ignored() {
+        let mut ctx = context();
+        let log = Log::default();
+        let mut stack = StateStack::new();
+        stack.push(&mut ctx, Box::new(Logged {
+            transition: Some(Transition::Pop),
+            .. Logged::new("level", &log)
+        }));
+        stack.push(&mut ctx, Box::new(Logged {
+            update_below: true,
+            transition: Some(Transition::Push(Box::new(Logged::new("dialog", &log)))),
+            .. Logged::new("hud", &log)
+        }));
+        take(&log);
+
+        stack.update(&mut ctx, 0.1);
+        assert_eq!(take(&log), ["level update", "hud update", "hud pause", "dialog enter"]);
+        assert_eq!(stack.len(), 3);
+    }
+
+    #[test]
+    fn popping_the_last_state_quits() {
+        let mut ctx = context();
+        let log = Log::default();
+        let mut stack = StateStack::new();
+        stack.push(&mut ctx, Box::new(Logged {
+            transition: Some(Transition::Pop),
+            .. Logged::new("level", &log)
+        }));
+        assert!(!ctx.should_quit());
+
+        stack.update(&mut ctx, 0.1);
+        assert!(stack.is_empty());
+        assert!(ctx.should_quit());
+    }
+}
@@ -0,0 +1,212 @@
+pub mod stats;
+pub mod profiler;
+pub mod timer;
+pub mod tween;
+
+use std::time;
+use time::stats::FrameStats;
+use time::profiler::Profiler;
+
+const DEFAULT_TICK_RATE: u32 = 60;
+const DEFAULT_MAX_UPDATES_PER_FRAME: u32 = 5;
+const FRAME_HISTORY: usize = 300;
+
+pub struct Clock {
+    init_instant: time::Instant,
+    last_instant: time::Instant,
+    frame_durations: time::Duration,
+    residual_update_dt: time::Duration,
+    frame_count: usize,
+    target_update_dt: time::Duration,
+    max_updates_per_frame: u32,
+    updates_this_frame: u32,
+    time_scale: f32,
+    paused: bool,
+    stats: FrameStats,
+    profiler: Profiler,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            init_instant: time::Instant::now(),
+            last_instant: time::Instant::now(),
+            frame_durations: time::Duration::new(0, 0),
+            residual_update_dt: time::Duration::from_secs(0),
+            frame_count: 0,
+            target_update_dt: tick_rate_to_duration(DEFAULT_TICK_RATE),
+            max_updates_per_frame: DEFAULT_MAX_UPDATES_PER_FRAME,
+            updates_this_frame: 0,
+            time_scale: 1.0,
+            paused: false,
+            stats: FrameStats::new(FRAME_HISTORY),
+            profiler: Profiler::new(FRAME_HISTORY),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        let now = time::Instant::now();
+        let time_since_last = now - self.last_instant;
+        self.frame_durations = time_since_last;
+        self.last_instant = now;
+        self.updates_this_frame = 0;
+
+        self.stats.push(time_since_last);
+        self.profiler.end_frame(self.frame_count, time_since_last);
+        self.frame_count += 1;
+
+        if !self.paused {
+            self.residual_update_dt += f64_to_duration(duration_to_f64(time_since_last) * f64::from(self.time_scale));
+        }
+    }
+
+    /// Scales how fast simulation time passes: 0.5 for slow motion, 2.0 to
+    /// fast-forward. Fixed steps keep their length, fewer or more of them run.
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.max(0.0);
+    }
+
+    pub fn get_time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Stops simulation time: no fixed update runs until `resume`.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Starts measuring frames from now, dropping the time accumulated so
+    /// far. Called when the application starts running, so that the setup
+    /// isn't counted as the first frame.
+    pub fn reset(&mut self) {
+        self.last_instant = time::Instant::now();
+        self.residual_update_dt = time::Duration::from_secs(0);
+    }
+
+    /// Sets how many fixed simulation steps should run per second.
+    pub fn set_tick_rate(&mut self, ticks_per_second: u32) {
+        self.target_update_dt = tick_rate_to_duration(ticks_per_second);
+    }
+
+    pub fn get_tick_rate(&self) -> u32 {
+        (1.0 / duration_to_f64(self.target_update_dt)).round() as u32
+    }
+
+    /// Caps how many simulation steps a single frame may run to catch up.
+    /// Time beyond the cap is dropped so a long stall can't spiral.
+    pub fn set_max_updates_per_frame(&mut self, max_updates: u32) {
+        self.max_updates_per_frame = max_updates.max(1);
+    }
+
+    /// Length of one fixed simulation step, in seconds.
+    pub fn get_fixed_dt(&self) -> f32 {
+        duration_to_f64(self.target_update_dt) as f32
+    }
+
+    /// Returns `true` while a fixed step of accumulated time is still
+    /// pending, consuming it. Meant to drive a `while` loop once per frame.
+    pub fn check_update_time(&mut self) -> bool {
+        if self.updates_this_frame >= self.max_updates_per_frame {
+            let residual = duration_to_f64(self.residual_update_dt);
+            let step = duration_to_f64(self.target_update_dt);
+            self.residual_update_dt = f64_to_duration(residual % step);
+            return false;
+        }
+
+        if self.residual_update_dt >= self.target_update_dt {
+            self.residual_update_dt -= self.target_update_dt;
+            self.updates_this_frame += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drops the simulation time accumulated by `tick`, for frames whose
+    /// steps don't come from the clock (replays and `Application::run_frames`).
+    /// Otherwise it would all be caught up on the next real-time frame.
+    pub fn discard_update_time(&mut self) {
+        self.residual_update_dt = time::Duration::from_secs(0);
+    }
+
+    /// How far we are between the last simulation step and the next one,
+    /// in `[0, 1)`. Used to interpolate rendering between two states.
+    pub fn get_alpha(&self) -> f32 {
+        let alpha = duration_to_f64(self.residual_update_dt) / duration_to_f64(self.target_update_dt);
+        alpha.min(1.0) as f32
+    }
+
+    /// Frames per second, averaged over the frame history.
+    pub fn get_fps(&self) -> f64 {
+        let seconds_per_frame = duration_to_f64(self.stats.average());
+        if seconds_per_frame > 0.0 {
+            1.0 / seconds_per_frame
+        } else {
+            0.0
+        }
+    }
+
+    /// Duration of the last frame, in seconds.
+    pub fn get_delta(&self) -> f32 {
+        duration_to_f64(self.frame_durations) as f32
+    }
+
+    /// Duration of the last frame in simulation time, in seconds: scaled
+    /// by the time scale, and zero while paused.
+    pub fn get_scaled_delta(&self) -> f32 {
+        if self.paused {
+            0.0
+        } else {
+            self.get_delta() * self.time_scale
+        }
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    pub fn get_time_since_start(&self) -> f32 {
+        duration_to_f64(time::Instant::now() - self.init_instant) as f32
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    pub fn profiler_mut(&mut self) -> &mut Profiler {
+        &mut self.profiler
+    }
+
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn duration_to_f64(duration: time::Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
+pub fn f64_to_duration(seconds: f64) -> time::Duration {
+    let secs = seconds.trunc();
+    let nanos = (seconds - secs) * 1_000_000_000.0;
+    time::Duration::new(secs as u64, nanos as u32)
+}
+
+fn tick_rate_to_duration(ticks_per_second: u32) -> time::Duration {
+    f64_to_duration(1.0 / f64::from(ticks_per_second.max(1)))
+}
@@ -0,0 +1,209 @@
+use std::collections::{ BTreeSet, VecDeque };
+use std::fs::File;
+use std::io;
+use std::io::{ BufWriter, Write };
+use std::path::Path;
+use std::time::{ Duration, Instant };
+
+use time::duration_to_f64;
+
+
+/// CPU time spent in each named scope during one frame.
+#[derive(Debug, Clone)]
+pub struct FrameProfile {
+    pub frame: usize,
+    pub duration: Duration,
+    pub scopes: Vec<(String, Duration)>,
+}
+
+impl FrameProfile {
+    /// Total time spent in `name` this frame, summed over every time it was entered.
+    pub fn scope(&self, name: &str) -> Option<Duration> {
+        let mut total = None;
+        for &(ref scope, duration) in self.scopes.iter() {
+            if scope == name {
+                total = Some(total.unwrap_or_default() + duration);
+            }
+        }
+        total
+    }
+}
+
+/// Records named CPU scopes per frame and keeps the last `capacity` frames.
+///
+/// Scopes are opened with `begin` and closed with `end`; nested scopes are
+/// recorded as `"outer/inner"`.
+pub struct Profiler {
+    open: Vec<(String, Instant)>,
+    current: Vec<(String, Duration)>,
+    frames: VecDeque<FrameProfile>,
+    capacity: usize,
+    pub enabled: bool,
+}
+
+impl Profiler {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            open: Vec::new(),
+            current: Vec::new(),
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            enabled: true,
+        }
+    }
+
+    pub fn begin(&mut self, name: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        let name = match self.open.last() {
+            Some((parent, _)) => format!("{}/{}", parent, name),
+            None => name.to_owned(),
+        };
+        self.open.push((name, Instant::now()));
+    }
+
+    /// Closes the innermost open scope.
+    pub fn end(&mut self) {
+        if let Some((name, start)) = self.open.pop() {
+            self.current.push((name, start.elapsed()));
+        }
+    }
+
+    /// Moves the scopes recorded so far into the history, as frame `frame`.
+    /// Called by `Clock::tick`.
+    pub fn end_frame(&mut self, frame: usize, duration: Duration) {
+        while !self.open.is_empty() {
+            self.end();
+        }
+
+        if !self.enabled {
+            return;
+        }
+
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+
+        let scopes = self.current.drain(..).collect();
+        self.frames.push_back(FrameProfile {
+            frame,
+            duration,
+            scopes,
+        });
+    }
+
+    pub fn frames(&self) -> &VecDeque<FrameProfile> {
+        &self.frames
+    }
+
+    pub fn last_frame(&self) -> Option<&FrameProfile> {
+        self.frames.back()
+    }
+
+    /// Average time per frame spent in `name`, over the frames it appears in.
+    pub fn average(&self, name: &str) -> Option<Duration> {
+        let samples: Vec<Duration> = self.frames.iter().filter_map(|frame| frame.scope(name)).collect();
+        if samples.is_empty() {
+            return None;
+        }
+        Some(samples.iter().sum::<Duration>() / samples.len() as u32)
+    }
+
+    /// Writes one row per recorded frame: frame number, frame time, then one
+    /// column per scope, all in milliseconds.
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let names: BTreeSet<&str> = self.frames.iter()
+            .flat_map(|frame| frame.scopes.iter().map(|(name, _)| name.as_str()))
+            .collect();
+
+        write!(writer, "frame,frame_ms")?;
+        for name in names.iter() {
+            write!(writer, ",{}_ms", name)?;
+        }
+        writeln!(writer)?;
+
+        for frame in self.frames.iter() {
+            write!(writer, "{},{:.3}", frame.frame, to_ms(frame.duration))?;
+            for name in names.iter() {
+                match frame.scope(name) {
+                    Some(duration) => write!(writer, ",{:.3}", to_ms(duration))?,
+                    None => write!(writer, ",")?,
+                }
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut writer)
+    }
+}
+
+fn to_ms(duration: Duration) -> f64 {
+    duration_to_f64(duration) * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(frame: usize, millis: u64, scopes: &[(&str, u64)]) -> FrameProfile {
+        FrameProfile {
+            frame,
+            duration: Duration::from_millis(millis),
+            scopes: scopes.iter()
+                .map(|&(name, millis)| (name.to_owned(), Duration::from_millis(millis)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn scopes_nest_and_sum() {
+        let mut profiler = Profiler::new(4);
+        profiler.begin("update");
+        profiler.begin("physics");
+        profiler.end();
+        profiler.begin("physics");
+        profiler.end();
+        profiler.end();
+        profiler.end_frame(0, Duration::from_millis(16));
+
+        let last = profiler.last_frame().unwrap();
+        let names: Vec<&str> = last.scopes.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["update/physics", "update/physics", "update"]);
+        assert!(last.scope("update/physics").is_some());
+        assert!(last.scope("physics").is_none());
+    }
+
+    #[test]
+    fn csv_has_a_column_per_scope_and_a_row_per_frame() {
+        let mut profiler = Profiler::new(4);
+        profiler.frames.push_back(frame(7, 16, &[("update", 2), ("draw", 5), ("update", 1)]));
+        profiler.frames.push_back(frame(8, 20, &[("draw", 6)]));
+
+        let mut csv = Vec::new();
+        profiler.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "frame,frame_ms,draw_ms,update_ms\n\
+             7,16.000,5.000,3.000\n\
+             8,20.000,6.000,\n"
+        );
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut profiler = Profiler::new(2);
+        for index in 0..5 {
+            profiler.end_frame(index, Duration::from_millis(1));
+        }
+        let frames: Vec<usize> = profiler.frames().iter().map(|frame| frame.frame).collect();
+        assert_eq!(frames, [3, 4]);
+    }
+}
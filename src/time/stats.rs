@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+
+/// Rolling window over the last frame times.
+pub struct FrameStats {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn last(&self) -> Duration {
+        self.samples.back().cloned().unwrap_or_default()
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::default();
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    pub fn min(&self) -> Duration {
+        self.samples.iter().min().cloned().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.iter().max().cloned().unwrap_or_default()
+    }
+
+    /// Frame time below which `percent` of the window falls, using the
+    /// nearest-rank method.
+    pub fn percentile(&self, percent: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::default();
+        }
+
+        let mut sorted: Vec<Duration> = self.samples.iter().cloned().collect();
+        sorted.sort();
+
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted[rank.max(1) - 1]
+    }
+
+    pub fn p95(&self) -> Duration {
+        self.percentile(95.0)
+    }
+
+    pub fn p99(&self) -> Duration {
+        self.percentile(99.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn empty_window_is_zero() {
+        let stats = FrameStats::new(10);
+        assert_eq!(stats.percentile(50.0), Duration::default());
+        assert_eq!(stats.p99(), Duration::default());
+    }
+
+    #[test]
+    fn single_sample_is_every_percentile() {
+        let mut stats = FrameStats::new(10);
+        stats.push(ms(16));
+        assert_eq!(stats.percentile(0.0), ms(16));
+        assert_eq!(stats.percentile(50.0), ms(16));
+        assert_eq!(stats.percentile(100.0), ms(16));
+    }
+
+    #[test]
+    fn nearest_rank() {
+        let mut stats = FrameStats::new(10);
+        for millis in [5, 1, 4, 2, 3].iter() {
+            stats.push(ms(*millis));
+        }
+        assert_eq!(stats.percentile(20.0), ms(1));
+        assert_eq!(stats.percentile(21.0), ms(2));
+        assert_eq!(stats.percentile(50.0), ms(3));
+        assert_eq!(stats.percentile(100.0), ms(5));
+    }
+
+    #[test]
+    fn p99_of_a_short_window_is_the_slowest_frame() {
+        let mut stats = FrameStats::new(100);
+        for millis in 1..51 {
+            stats.push(ms(millis));
+        }
+        assert_eq!(stats.p99(), ms(50));
+        assert_eq!(stats.p95(), ms(48));
+    }
+
+    #[test]
+    fn window_drops_oldest_samples() {
+        let mut stats = FrameStats::new(3);
+        for millis in 1..6 {
+            stats.push(ms(millis));
+        }
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.min(), ms(3));
+        assert_eq!(stats.max(), ms(5));
+        assert_eq!(stats.average(), ms(4));
+    }
+}
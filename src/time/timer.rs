@@ -0,0 +1,313 @@
+use std::mem;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+
+/// Ids are unique across every `Timers`, so timers can move between them.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Handle returned when scheduling a timer, used to cancel it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(usize);
+
+type Callback<T> = Box<FnMut(&mut T, &mut Timers<T>)>;
+
+struct Timer<T> {
+    id: TimerId,
+    remaining: f32,
+    /// `None` for one-shot timers.
+    interval: Option<f32>,
+    /// Taken out while it runs.
+    callback: Option<Callback<T>>,
+}
+
+/// Scheduled callbacks, advanced by `update` with the simulation `dt`.
+///
+/// Callbacks get mutable access to the `T` passed to `update`, usually
+/// the game state owning the timers, and to the timers themselves to
+/// schedule or cancel others. Timers stored in the state they drive are
+/// advanced with `update_owned` from `EventHandler::update`, so they follow
+/// the fixed step, the time scale and pauses like the rest of the simulation:
+///
+/// ```ignore
+/// fn update(&mut self, ctx: &mut Context, dt: f32) {
+///     Timers::update_owned(self, dt, |state| &mut state.timers);
+/// }
+///
+/// self.timers.every(1.0, |state: &mut State, timers: &mut Timers<State>| {
+///     state.spawn_enemy();
+///     timers.after(0.5, |state: &mut State, _: &mut Timers<State>| state.play_warning());
+/// });
+/// ```
+pub struct Timers<T> {
+    timers: Vec<Timer<T>>,
+}
+
+impl<T> Timers<T> {
+    pub fn new() -> Self {
+        Self {
+            timers: Vec::new(),
+        }
+    }
+
+    /// Calls `callback` once, `delay` seconds from now.
+    pub fn after<F: FnMut(&mut T, &mut Timers<T>) + 'static>(&mut self, delay: f32, callback: F) -> TimerId {
+        self.schedule(delay, None, Box::new(callback))
+    }
+
+    /// Calls `callback` every `interval` seconds until cancelled.
+    pub fn every<F: FnMut(&mut T, &mut Timers<T>) + 'static>(&mut self, interval: f32, callback: F) -> TimerId {
+        self.schedule(interval, Some(interval), Box::new(callback))
+    }
+
+    /// Returns `false` if the timer already fired or was cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let len = self.timers.len();
+        self.timers.retain(|timer| timer.id != id);
+        self.timers.len() != len
+    }
+
+    pub fn is_active(&self, id: TimerId) -> bool {
+        self.timers.iter().any(|timer| timer.id == id)
+    }
+
+    /// Seconds left before the timer next fires.
+    pub fn remaining(&self, id: TimerId) -> Option<f32> {
+        self.timers.iter()
+            .find(|timer| timer.id == id)
+            .map(|timer| timer.remaining.max(0.0))
+    }
+
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+
+    /// Advances every timer by `dt` seconds and runs the ones due. A
+    /// repeating timer fires as many times as intervals elapsed, but at
+    /// most once per update if its interval is not positive.
+    ///
+    /// Timers scheduled by the callbacks start counting at the next update,
+    /// and a timer cancelled by a callback doesn't fire again.
+    pub fn update(&mut self, dt: f32, target: &mut T) {
+        for timer in self.timers.iter_mut() {
+            timer.remaining -= dt;
+        }
+
+        let due: Vec<TimerId> = self.timers.iter()
+            .filter(|timer| timer.remaining <= 0.0)
+            .map(|timer| timer.id)
+            .collect();
+
+        for id in due {
+            while let Some(index) = self.position(id) {
+                if self.timers[index].remaining > 0.0 {
+                    break;
+                }
+                let mut callback = match self.timers[index].callback.take() {
+                    Some(callback) => callback,
+                    None => break,
+                };
+
+                callback(target, self);
+
+                // The callback may have cancelled its own timer, or any other.
+                let index = match self.position(id) {
+                    Some(index) => index,
+                    None => break,
+                };
+                let interval = {
+                    let timer = &mut self.timers[index];
+                    timer.callback = Some(callback);
+                    timer.interval
+                };
+                match interval {
+                    Some(interval) if interval > 0.0 => self.timers[index].remaining += interval,
+                    Some(interval) => {
+                        self.timers[index].remaining = interval;
+                        break;
+                    },
+                    None => {
+                        self.timers.remove(index);
+                        break;
+                    },
+                }
+            }
+        }
+    }
+
+    /// Runs `update` on timers stored inside `owner`, which `timers` returns,
+    /// with `owner` as the callbacks' target.
+    ///
+    /// The timers are moved out of `owner` while they run: callbacks must
+    /// cancel them through the `Timers` they are given. Timers scheduled on
+    /// `owner` meanwhile are kept and start counting at the next update.
+    pub fn update_owned<F: Fn(&mut T) -> &mut Timers<T>>(owner: &mut T, dt: f32, timers: F) {
+        let mut running = mem::take(timers(owner));
+        running.update(dt, owner);
+
+        let scheduled = mem::replace(timers(owner), running);
+        timers(owner).timers.extend(scheduled.timers);
+    }
+
+    fn position(&self, id: TimerId) -> Option<usize> {
+        self.timers.iter().position(|timer| timer.id == id)
+    }
+
+    fn schedule(&mut self, delay: f32, interval: Option<f32>, callback: Callback<T>) -> TimerId {
+        let id = TimerId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+        self.timers.push(Timer {
+            id,
+            remaining: delay,
+            interval,
+            callback: Some(callback),
+        });
+
+        id
+    }
+}
+
+impl<T> Default for Timers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    type Log = Vec<&'static str>;
+
+    #[test]
+    fn one_shot_fires_once_after_its_delay() {
+        let mut timers = Timers::new();
+        let mut log = Log::new();
+        let id = timers.after(1.0, |log: &mut Log, _: &mut Timers<Log>| log.push("once"));
+
+        timers.update(0.5, &mut log);
+        assert!(log.is_empty());
+        assert_eq!(timers.remaining(id), Some(0.5));
+
+        timers.update(0.5, &mut log);
+        timers.update(1.0, &mut log);
+        assert_eq!(log, vec!["once"]);
+        assert!(!timers.is_active(id));
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn repeating_fires_once_per_elapsed_interval() {
+        let mut timers = Timers::new();
+        let mut count = 0;
+        let id = timers.every(0.25, |count: &mut u32, _: &mut Timers<u32>| *count += 1);
+
+        timers.update(0.125, &mut count);
+        assert_eq!(count, 0);
+        timers.update(0.5, &mut count);
+        assert_eq!(count, 2);
+        assert_eq!(timers.remaining(id), Some(0.125));
+
+        assert!(timers.cancel(id));
+        timers.update(1.0, &mut count);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn non_positive_interval_fires_once_per_update() {
+        let mut timers = Timers::new();
+        let mut count = 0;
+        timers.every(0.0, |count: &mut u32, _: &mut Timers<u32>| *count += 1);
+
+        timers.update(1.0, &mut count);
+        timers.update(1.0, &mut count);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn callback_can_cancel_its_own_timer() {
+        let mut timers = Timers::new();
+        let mut count = 0;
+        timers.every(0.25, |count: &mut u32, timers: &mut Timers<u32>| {
+            *count += 1;
+            if *count == 2 {
+                timers.clear();
+            }
+        });
+
+        timers.update(2.0, &mut count);
+        assert_eq!(count, 2);
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn callback_can_cancel_a_due_timer() {
+        let mut timers = Timers::new();
+        let mut log = Log::new();
+        let second = Rc::new(Cell::new(None));
+        let to_cancel = second.clone();
+        timers.after(1.0, move |log: &mut Log, timers: &mut Timers<Log>| {
+            log.push("first");
+            assert!(timers.cancel(to_cancel.get().unwrap()));
+        });
+        second.set(Some(timers.after(1.0, |log: &mut Log, _: &mut Timers<Log>| log.push("second"))));
+
+        timers.update(1.0, &mut log);
+        assert_eq!(log, vec!["first"]);
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn timers_scheduled_by_callbacks_start_at_the_next_update() {
+        let mut timers = Timers::new();
+        let mut log = Log::new();
+        let first = timers.after(1.0, |log: &mut Log, timers: &mut Timers<Log>| {
+            log.push("first");
+            timers.after(0.0, |log: &mut Log, _: &mut Timers<Log>| log.push("scheduled"));
+        });
+
+        timers.update(1.0, &mut log);
+        assert_eq!(log, vec!["first"]);
+        assert_eq!(timers.len(), 1);
+        assert!(!timers.is_active(first));
+
+        let next = timers.after(1.0, |_: &mut Log, _: &mut Timers<Log>| {});
+        assert_ne!(next, first);
+
+        timers.update(0.0, &mut log);
+        assert_eq!(log, vec!["first", "scheduled"]);
+    }
+
+    struct Owner {
+        log: Log,
+        timers: Timers<Owner>,
+    }
+
+    #[test]
+    fn owned_timers_drive_their_owner() {
+        let mut owner = Owner { log: Log::new(), timers: Timers::new() };
+        let every = owner.timers.every(1.0, |owner: &mut Owner, _: &mut Timers<Owner>| {
+            owner.log.push("every");
+            // Scheduled on the owner while its timers are running.
+            owner.timers.after(0.5, |owner: &mut Owner, _: &mut Timers<Owner>| owner.log.push("after"));
+        });
+
+        Timers::update_owned(&mut owner, 1.0, |owner| &mut owner.timers);
+        assert_eq!(owner.log, vec!["every"]);
+        assert_eq!(owner.timers.len(), 2);
+        assert!(owner.timers.is_active(every));
+
+        Timers::update_owned(&mut owner, 0.5, |owner| &mut owner.timers);
+        assert_eq!(owner.log, vec!["every", "after"]);
+        assert_eq!(owner.timers.len(), 1);
+    }
+}
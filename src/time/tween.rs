@@ -0,0 +1,284 @@
+use std::f32::consts::PI;
+
+use cgmath::Vector3;
+use render::transform::Transform;
+
+
+/// Values a `Tween` can interpolate.
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vector3<f32> {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f32; 3] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        [
+            self[0].lerp(other[0], t),
+            self[1].lerp(other[1], t),
+            self[2].lerp(other[2], t),
+        ]
+    }
+}
+
+impl Lerp for [f32; 4] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        [
+            self[0].lerp(other[0], t),
+            self[1].lerp(other[1], t),
+            self[2].lerp(other[2], t),
+            self[3].lerp(other[3], t),
+        ]
+    }
+}
+
+/// Easing curves, mapping linear progress in `[0, 1]` to eased progress.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    /// Overshoots the target slightly before settling.
+    BackOut,
+    BounceOut,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => t * (2.0 - t),
+            Easing::QuadInOut => if t < 0.5 {
+                2.0 * t * t
+            } else {
+                -1.0 + (4.0 - 2.0 * t) * t
+            },
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => {
+                let t = t - 1.0;
+                t * t * t + 1.0
+            },
+            Easing::CubicInOut => if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                let t = 2.0 * t - 2.0;
+                0.5 * t * t * t + 1.0
+            },
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => 0.5 * (1.0 - (t * PI).cos()),
+            Easing::BackOut => {
+                let s = 1.70158;
+                let t = t - 1.0;
+                t * t * ((s + 1.0) * t + s) + 1.0
+            },
+            Easing::BounceOut => {
+                if t < 1.0 / 2.75 {
+                    7.5625 * t * t
+                } else if t < 2.0 / 2.75 {
+                    let t = t - 1.5 / 2.75;
+                    7.5625 * t * t + 0.75
+                } else if t < 2.5 / 2.75 {
+                    let t = t - 2.25 / 2.75;
+                    7.5625 * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / 2.75;
+                    7.5625 * t * t + 0.984375
+                }
+            },
+        }
+    }
+}
+
+/// Animates a value from `from` to `to` over `duration` seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct Tween<T: Lerp> {
+    pub from: T,
+    pub to: T,
+    pub duration: f32,
+    pub easing: Easing,
+    elapsed: f32,
+    looping: bool,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing,
+            elapsed: 0.0,
+            looping: false,
+        }
+    }
+
+    /// Restarts from `from` each time the end is reached. The update that
+    /// reaches the end still returns `to`, the time past it is carried over
+    /// to the next loop.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Advances the tween by `dt` seconds and returns the new value.
+    pub fn update(&mut self, dt: f32) -> T {
+        if self.looping && self.duration > 0.0 && self.elapsed >= self.duration {
+            self.elapsed %= self.duration;
+        }
+        self.elapsed += dt;
+        self.value()
+    }
+
+    pub fn value(&self) -> T {
+        self.from.lerp(self.to, self.easing.apply(self.progress()))
+    }
+
+    /// Linear progress in `[0, 1]`.
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Never true for a looping tween.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.progress() >= 1.0
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+
+    /// Same tween going from `to` back to `from`.
+    pub fn reversed(&self) -> Self {
+        Self {
+            from: self.to,
+            to: self.from,
+            elapsed: 0.0,
+            .. *self
+        }
+    }
+}
+
+impl Tween<Vector3<f32>> {
+    pub fn apply_position(&self, transform: &mut Transform) {
+        transform.position = self.value();
+    }
+}
+
+impl Tween<f32> {
+    /// Sets a uniform scale.
+    pub fn apply_scale(&self, transform: &mut Transform) {
+        transform.scale(self.value());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EASINGS: [Easing; 12] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::SineIn,
+        Easing::SineOut,
+        Easing::SineInOut,
+        Easing::BackOut,
+        Easing::BounceOut,
+    ];
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-5, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        for easing in EASINGS.iter() {
+            assert_close(easing.apply(0.0), 0.0);
+            assert_close(easing.apply(1.0), 1.0);
+            // Progress outside `[0, 1]` is clamped.
+            assert_close(easing.apply(-0.5), 0.0);
+            assert_close(easing.apply(1.5), 1.0);
+        }
+    }
+
+    #[test]
+    fn in_out_easings_are_halfway_at_the_middle() {
+        for easing in [Easing::Linear, Easing::QuadInOut, Easing::CubicInOut, Easing::SineInOut].iter() {
+            assert_close(easing.apply(0.5), 0.5);
+        }
+    }
+
+    #[test]
+    fn finishes_on_the_end_value() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0, Easing::Linear);
+        assert_close(tween.update(0.25), 2.5);
+        assert!(!tween.is_finished());
+
+        assert_close(tween.update(1.0), 10.0);
+        assert!(tween.is_finished());
+        assert_close(tween.update(1.0), 10.0);
+
+        tween.reset();
+        assert_close(tween.value(), 0.0);
+        assert!(!tween.is_finished());
+    }
+
+    #[test]
+    fn zero_duration_is_finished_right_away() {
+        let tween = Tween::new(1.0, 2.0, 0.0, Easing::QuadIn);
+        assert_close(tween.value(), 2.0);
+        assert!(tween.is_finished());
+    }
+
+    #[test]
+    fn looping_reaches_the_end_value_then_restarts() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0, Easing::Linear).looping(true);
+        assert_close(tween.update(0.5), 5.0);
+        assert_close(tween.update(0.75), 10.0);
+        assert!(!tween.is_finished());
+
+        // The 0.25 past the end carries over.
+        assert_close(tween.update(0.5), 7.5);
+        assert_close(tween.update(0.25), 10.0);
+        assert_close(tween.update(0.25), 2.5);
+        assert!(!tween.is_finished());
+    }
+
+    #[test]
+    fn reversed_swaps_the_ends_and_restarts() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0, Easing::Linear);
+        tween.update(0.5);
+
+        let mut reversed = tween.reversed();
+        assert_close(reversed.value(), 10.0);
+        assert_close(reversed.update(0.25), 7.5);
+    }
+}